@@ -0,0 +1,46 @@
+use core::slice::IterMut;
+use serde::Serialize;
+use zkwasm_rest_abi::StorageData;
+use zkwasm_rest_convention::IndexedObject;
+
+// achievement ids, each one is a bit of PlayerData::achievements
+pub const ACHIEVEMENT_FIRST_SALE: u64 = 0;
+pub const ACHIEVEMENT_FULLY_EXPLORED: u64 = 1;
+pub const ACHIEVEMENT_TEN_TRADES: u64 = 2;
+pub const ACHIEVEMENT_LEGENDARY: u64 = 3;
+
+// sales and purchases needed for ACHIEVEMENT_TEN_TRADES
+pub const TRADES_ACHIEVEMENT_COUNT: u64 = 10;
+
+/// Emitted when a player unlocks an achievement.
+#[derive(Clone, Serialize, Default)]
+pub struct AchievementUnlocked {
+    pub owner: [u64; 2],
+    pub achievement: u64,
+    pub reward: u64,
+}
+
+impl StorageData for AchievementUnlocked {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let owner = [*u64data.next().unwrap(), *u64data.next().unwrap()];
+        let achievement = *u64data.next().unwrap();
+        let reward = *u64data.next().unwrap();
+        AchievementUnlocked {
+            owner,
+            achievement,
+            reward,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.owner[0]);
+        data.push(self.owner[1]);
+        data.push(self.achievement);
+        data.push(self.reward);
+    }
+}
+
+impl IndexedObject<AchievementUnlocked> for AchievementUnlocked {
+    const PREFIX: u64 = 0x1ee9;
+    const POSTFIX: u64 = 0xfee9;
+    const EVENT_NAME: u64 = 0x0a;
+}
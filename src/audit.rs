@@ -0,0 +1,61 @@
+use core::slice::IterMut;
+use serde::Serialize;
+use zkwasm_rest_abi::StorageData;
+use zkwasm_rest_convention::IndexedObject;
+use crate::state::GLOBAL_STATE;
+
+// audited admin actions
+pub const AUDIT_FREEZE: u64 = 0;
+pub const AUDIT_UNFREEZE: u64 = 1;
+
+/// Trail of admin actions taken against players, one entry per action.
+#[derive(Clone, Serialize, Default)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub action: u64,
+    pub target: [u64; 2],
+    pub counter: u64,
+}
+
+impl StorageData for AuditEntry {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let id = *u64data.next().unwrap();
+        let action = *u64data.next().unwrap();
+        let target = [*u64data.next().unwrap(), *u64data.next().unwrap()];
+        let counter = *u64data.next().unwrap();
+        AuditEntry {
+            id,
+            action,
+            target,
+            counter,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.id);
+        data.push(self.action);
+        data.push(self.target[0]);
+        data.push(self.target[1]);
+        data.push(self.counter);
+    }
+}
+
+impl AuditEntry {
+    pub fn record(action: u64, target: &[u64; 2], counter: u64) {
+        let mut global = GLOBAL_STATE.0.borrow_mut();
+        let entry = AuditEntry {
+            id: global.audit_total,
+            action,
+            target: *target,
+            counter,
+        };
+        global.audit_total += 1;
+        AuditEntry::new_object(entry.clone(), entry.id).store();
+        AuditEntry::emit_event(entry.id, &entry);
+    }
+}
+
+impl IndexedObject<AuditEntry> for AuditEntry {
+    const PREFIX: u64 = 0x1eeb;
+    const POSTFIX: u64 = 0xfeeb;
+    const EVENT_NAME: u64 = 0x0b;
+}
@@ -1,10 +1,24 @@
-use crate::nugget::{BidInfo, NuggetInfo};
+use crate::config::{
+    CreationConfig, ExploreCost, ADMIN_PUBKEY, CHALLENGE_EXPIRY_TICKS, CONFIG, EXPEDITION_FEE, INSURANCE_PREMIUM_PERCENT,
+    MAX_EXPEDITION_TICKS, MAX_SESSION_TICKS, MAX_TOOLS, MIN_EXPEDITION_TICKS, REFERRAL_FEE_PERCENT, WAREHOUSE_FEE,
+    XP_CREATE, XP_EXPLORE, XP_TRADE,
+};
+use crate::duel::{duel_winner, ChallengeInfo, CHALLENGE_CANCELLED, CHALLENGE_PENDING, CHALLENGE_RESOLVED, DUEL_CHALLENGER};
+use crate::expedition::ExpeditionInfo;
+use crate::nugget::{
+    BidInfo, ExploreResult, NuggetInfo, NUGGET_ACTIVE, NUGGET_CONSUMED, NUGGET_DUEL, NUGGET_EXPEDITION,
+    NUGGET_RECYCLED, NUGGET_STORED,
+};
 use zkwasm_rest_convention::{IndexedObject, Position};
 use zkwasm_rust_sdk::require;
 use zkwasm_rest_abi::WithdrawInfo;
 use crate::settlement::SettlementInfo;
-use crate::player::GamePlayer;
+use crate::player::{GamePlayer, NuggetRef, PlayerData};
+use crate::tool::{ToolInfo, TOOL_DRILL, TOOL_SCANNER};
 use crate::state::{GlobalState, GLOBAL_STATE};
+use crate::warehouse::WarehouseInfo;
+use crate::session::{SessionInfo, SESSION_CREATE, SESSION_DUEL, SESSION_EXPLORE, SESSION_MANAGE, SESSION_TRADE};
+use crate::audit::{AuditEntry, AUDIT_FREEZE, AUDIT_UNFREEZE};
 use crate::error::*;
 
 #[derive (Clone)]
@@ -14,8 +28,14 @@ pub enum Command {
     // standard withdraw and deposit
     Withdraw(Withdraw),
     Deposit(Deposit),
+    // admin configuration
+    SetExploreCost(SetExploreCost),
+    SetCreationConfig(SetCreationConfig),
+    SetTradeFee(SetTradeFee),
+    // admin moderation
+    FreezePlayer(FreezePlayer),
     // standard player install and timer
-    InstallPlayer,
+    InstallPlayer(Option<[u64; 2]>),
     Tick,
 }
 
@@ -35,6 +55,9 @@ impl CommandHandler for Withdraw {
         match player.as_mut() {
             None => Err(ERROR_PLAYER_NOT_EXIST),
             Some(player) => {
+                if player.data.frozen {
+                    return Err(ERROR_PLAYER_FROZEN);
+                }
                 player.check_and_inc_nonce(nonce);
                 let balance = player.data.balance;
                 let amount = self.data[0] & 0xffffffff;
@@ -72,13 +95,136 @@ impl CommandHandler for Deposit {
     }
 }
 
+#[derive (Clone)]
+pub struct SetExploreCost {
+    pub data: [u64; 4],
+}
+
+impl CommandHandler for SetExploreCost {
+    fn handle(&self, pid: &[u64; 2], nonce: u64, _rand: &[u64; 4], _counter: u64) -> Result<(), u32> {
+        let explore_cost = ExploreCost {
+            base: self.data[0],
+            per_attribute: self.data[1],
+            percent: self.data[2],
+            cap: self.data[3],
+        };
+        if !explore_cost.is_valid() {
+            return Err(ERROR_INVALID_EXPLORE_COST);
+        }
+        let mut admin = GamePlayer::get_from_pid(pid).unwrap();
+        admin.check_and_inc_nonce(nonce);
+        GLOBAL_STATE.0.borrow_mut().explore_cost = explore_cost;
+        admin.store();
+        Ok(())
+    }
+}
+
+#[derive (Clone)]
+pub struct SetCreationConfig {
+    pub data: [u64; 4],
+}
+
+impl CommandHandler for SetCreationConfig {
+    fn handle(&self, pid: &[u64; 2], nonce: u64, _rand: &[u64; 4], _counter: u64) -> Result<(), u32> {
+        // the cap can stop creation but not fall below the nuggets already minted
+        if self.data[2] < GLOBAL_STATE.0.borrow().total {
+            return Err(ERROR_INVALID_CREATION_CONFIG);
+        }
+        let mut admin = GamePlayer::get_from_pid(pid).unwrap();
+        admin.check_and_inc_nonce(nonce);
+        GLOBAL_STATE.0.borrow_mut().creation = CreationConfig {
+            fee: self.data[0],
+            fee_step: self.data[1],
+            supply_cap: self.data[2],
+            cooldown: self.data[3],
+        };
+        admin.store();
+        Ok(())
+    }
+}
+
+/// Sets the percent of each bid sale paid into the airdrop pool.
+#[derive (Clone)]
+pub struct SetTradeFee {
+    pub percent: u64,
+}
+
+impl CommandHandler for SetTradeFee {
+    fn handle(&self, pid: &[u64; 2], nonce: u64, _rand: &[u64; 4], _counter: u64) -> Result<(), u32> {
+        if self.percent > 100 {
+            return Err(ERROR_INVALID_TRADE_FEE);
+        }
+        let mut admin = GamePlayer::get_from_pid(pid).unwrap();
+        admin.check_and_inc_nonce(nonce);
+        GLOBAL_STATE.0.borrow_mut().trade_fee_percent = self.percent;
+        admin.store();
+        Ok(())
+    }
+}
+
+/// Freezes or unfreezes the player `pid`, a frozen player can neither run
+/// activities nor withdraw.
+#[derive (Clone)]
+pub struct FreezePlayer {
+    pub pid: [u64; 2],
+    pub frozen: bool,
+}
+
+impl CommandHandler for FreezePlayer {
+    fn handle(&self, pid: &[u64; 2], nonce: u64, _rand: &[u64; 4], counter: u64) -> Result<(), u32> {
+        let mut admin = GamePlayer::get_from_pid(pid).unwrap();
+        admin.check_and_inc_nonce(nonce);
+        let mut player = GamePlayer::get_from_pid(&self.pid).ok_or(ERROR_PLAYER_NOT_EXIST)?;
+        player.data.frozen = self.frozen;
+        player.store();
+        AuditEntry::record(if self.frozen { AUDIT_FREEZE } else { AUDIT_UNFREEZE }, &self.pid, counter);
+        admin.store();
+        Ok(())
+    }
+}
+
 #[derive (Clone)]
 pub enum Activity {
     // activities
     Create,
     Bid(u64, u64),
-    Sell(u64),
-    Explore(u64),
+    Sell(NuggetRef),
+    Explore(NuggetRef, Option<u64>, bool),
+    Refine(NuggetRef),
+    Reroll(NuggetRef, u64),
+    Maintain(NuggetRef),
+    Stake(NuggetRef),
+    Unstake(NuggetRef),
+    Collect,
+    Expedition(NuggetRef, u64),
+    Craft(u64, Vec<NuggetRef>),
+    BuyTool(u64),
+    Challenge(NuggetRef, [u64; 2], u64, bool),
+    AcceptChallenge(u64, NuggetRef),
+    CancelChallenge(u64),
+    UpgradeInventory,
+    Store(NuggetRef),
+    Retrieve(u64),
+    ClaimDaily,
+    RegisterSession([u64; 2], u64, u64),
+    RevokeSession([u64; 2]),
+}
+
+impl Activity {
+    /// Session permission bit required to run the activity through a session
+    /// key, zero for activities only the main key may run.
+    pub fn permission(&self) -> u64 {
+        match self {
+            Activity::Explore(..) | Activity::Refine(_) | Activity::Reroll(..) | Activity::Maintain(_) => SESSION_EXPLORE,
+            Activity::Create | Activity::Craft(..) => SESSION_CREATE,
+            Activity::Bid(..) | Activity::Sell(_) => SESSION_TRADE,
+            Activity::Stake(_) | Activity::Unstake(_) | Activity::Collect | Activity::Expedition(..)
+                | Activity::BuyTool(_) | Activity::UpgradeInventory | Activity::Store(_)
+                | Activity::Retrieve(_) | Activity::ClaimDaily => SESSION_MANAGE,
+            Activity::Challenge(..) | Activity::AcceptChallenge(..) | Activity::CancelChallenge(_) => SESSION_DUEL,
+            Activity::RegisterSession(..) | Activity::RevokeSession(_) => 0,
+        }
+    }
 }
 
 impl CommandHandler for Activity {
@@ -87,102 +233,555 @@ impl CommandHandler for Activity {
         let mut player = GamePlayer::get_from_pid(pid);
         match player.as_mut() {
             None => Err(ERROR_PLAYER_NOT_EXIST),
+            Some(player) if player.data.frozen => Err(ERROR_PLAYER_FROZEN),
             Some(player) => {
                 match self {
                     Activity::Create => {
-                        if player.data.inventory.len() > player.data.inventory_size as usize {
+                        let creation = GlobalState::get_creation_config();
+                        let total = GLOBAL_STATE.0.borrow().total;
+                        if !player.data.has_inventory_space() {
                             Err(PLAYER_NOT_ENOUGH_INVENTORY)
+                        } else if total >= creation.supply_cap {
+                            Err(ERROR_NUGGET_SUPPLY_EXHAUSTED)
+                        } else if player.data.last_create > 0 && counter < player.data.last_create + creation.cooldown {
+                            Err(ERROR_CREATE_COOLDOWN)
                         } else {
+                            player.data.cost_balance(creation.creation_fee(total))?;
+                            player.data.last_create = counter;
                             let mut global = GLOBAL_STATE.0.borrow_mut();
-                            let mut nugget = NuggetInfo::new_object(NuggetInfo::new(global.total, rand[1]), global.total);
+                            let mut nugget = NuggetInfo::new_object(NuggetInfo::new(global.total, rand[1], counter), global.total);
                             nugget.data.compute_sysprice();
                             nugget.store();
                             NuggetInfo::emit_event(global.total, &nugget.data);
                             global.total += 1;
+                            drop(global);
+                            player.data.stats.created += 1;
+                            player.data.record_owned(pid, &nugget.data);
+                            player.data.gain_xp(pid, XP_CREATE);
                             player.data.inventory.push(nugget.data.id);
+                            player.data.check_collection_sets();
                             player.store();
                             Ok(())
                         }
                     },
 
-                    Activity::Explore(index) => {
-                        if player.data.inventory.len() <= (*index) as usize {
-                            Err(INVALID_NUGGET_INDEX)
+                    Activity::Explore(target, tool_id, insured) => {
+                        let nuggetid = player.data.inventory[player.data.locate_nugget(target)?];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        let mut cost = nugget.data.explore_cost(&GlobalState::get_explore_cost());
+                        cost -= cost * player.data.perks().explore_discount / 100;
+                        let mut scanned = false;
+                        let mut tool = None;
+                        if let Some(id) = tool_id {
+                            // tools are addressed by id so a tool breaking does not shift the others
+                            let slot = player.data.tools.iter().position(|t| *t == *id).ok_or(ERROR_INVALID_TOOL)?;
+                            let mut t = ToolInfo::get_object(*id).unwrap();
+                            match t.data.kind {
+                                TOOL_DRILL => cost /= 2,
+                                TOOL_SCANNER => scanned = true,
+                                _ => (),
+                            }
+                            if t.data.wear() {
+                                player.data.tools.remove(slot);
+                            }
+                            tool = Some(t);
+                        }
+                        let premium = if *insured { cost * INSURANCE_PREMIUM_PERCENT / 100 } else { 0 };
+                        player.data.cost_balance(cost + premium)?;
+                        let result = nugget.data.explore(rand[2], rand[3], player.data.pity_ready(), scanned)?;
+                        if let Some(t) = tool {
+                            ToolInfo::emit_event(t.data.id, &t.data);
+                            t.store();
+                        }
+                        if *insured {
+                            let mut global = GLOBAL_STATE.0.borrow_mut();
+                            global.insurance_pool += premium;
+                            let payout = result.insurance_payout(cost, global.insurance_pool);
+                            global.insurance_pool -= payout;
+                            player.data.inc_balance(payout);
+                        }
+                        player.data.record_explore(&result);
+                        player.data.gain_xp(pid, XP_EXPLORE);
+                        nugget.data.maintained = counter;
+                        nugget.data.compute_sysprice();
+                        player.data.record_owned(pid, &nugget.data);
+                        ExploreResult::emit_event(nugget.data.id, &result);
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Refine(target) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        player.data.cost_balance(nugget.data.refine_cost())?;
+                        nugget.data.refine()?;
+                        nugget.data.compute_sysprice();
+                        player.data.record_owned(pid, &nugget.data);
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Reroll(target, slot) => {
+                        let nuggetid = player.data.inventory[player.data.locate_nugget(target)?];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        player.data.cost_balance(nugget.data.reroll_cost(&GlobalState::get_explore_cost()))?;
+                        let result = nugget.data.reroll(*slot, rand[2])?;
+                        nugget.data.compute_sysprice();
+                        player.data.record_owned(pid, &nugget.data);
+                        ExploreResult::emit_event(nugget.data.id, &result);
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Maintain(target) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        player.data.cost_balance(nugget.data.maintain_cost(counter))?;
+                        nugget.data.maintained = counter;
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Stake(target) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        nugget.data.stake(counter)?;
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        Ok(())
+                    },
+
+                    Activity::Unstake(target) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        let amount = nugget.data.unstake(counter)?;
+                        player.data.inc_balance(amount);
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Collect => {
+                        let mut total = 0;
+                        for nuggetid in player.data.inventory.iter() {
+                            let mut nugget = NuggetInfo::get_object(*nuggetid).unwrap();
+                            let amount = nugget.data.collect_yield(counter);
+                            if amount > 0 {
+                                total += amount;
+                                nugget.store();
+                            }
+                        }
+                        if total == 0 {
+                            Err(NOTHING_TO_COLLECT)
                         } else {
-                            let nuggetid = player.data.inventory[*index as usize];
-                            let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
-                            player.data.cost_balance(nugget.data.sysprice / 4)?;
-                            nugget.data.explore(rand[2])?;
-                            nugget.data.compute_sysprice();
-                            NuggetInfo::emit_event(nugget.data.id, &nugget.data);
-                            nugget.store();
+                            player.data.inc_balance(total);
                             player.store();
                             Ok(())
                         }
                     },
 
-                    Activity::Sell(index) => {
-                        if player.data.inventory.len() <= (*index) as usize {
-                            Err(INVALID_NUGGET_INDEX)
+                    Activity::Expedition(target, duration) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        if *duration < MIN_EXPEDITION_TICKS || *duration > MAX_EXPEDITION_TICKS {
+                            return Err(ERROR_INVALID_EXPEDITION_DURATION);
+                        }
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        player.data.cost_balance(EXPEDITION_FEE)?;
+                        ExpeditionInfo::start(pid, nuggetid, counter, *duration)?;
+                        nugget.data.status = NUGGET_EXPEDITION;
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Craft(recipe_id, targets) => {
+                        let recipe = CONFIG.recipes.get(*recipe_id as usize).ok_or(ERROR_INVALID_RECIPE)?;
+                        if targets.len() < recipe.inputs.len() {
+                            return Err(INVALID_NUGGET_INDEX);
+                        }
+                        let mut slots = vec![];
+                        for target in targets[0..recipe.inputs.len()].iter() {
+                            slots.push(player.data.locate_nugget(target)?);
+                        }
+                        let mut inputs = vec![];
+                        for (slot, input) in slots.iter().zip(recipe.inputs.iter()) {
+                            let nugget = NuggetInfo::get_object(player.data.inventory[*slot]).unwrap();
+                            if nugget.data.is_locked() {
+                                return Err(ERROR_NUGGET_LOCKED);
+                            }
+                            if !input.accepts(&nugget.data) {
+                                return Err(ERROR_RECIPE_REQUIREMENT_NOT_MET);
+                            }
+                            inputs.push(nugget);
+                        }
+                        // the same nugget can not fill two inputs
+                        slots.sort_unstable();
+                        slots.dedup();
+                        if slots.len() < recipe.inputs.len() {
+                            return Err(INVALID_NUGGET_INDEX);
+                        }
+                        if GLOBAL_STATE.0.borrow().total >= GlobalState::get_creation_config().supply_cap {
+                            return Err(ERROR_NUGGET_SUPPLY_EXHAUSTED);
+                        }
+                        player.data.cost_balance(recipe.fee)?;
+                        for nugget in inputs.iter_mut() {
+                            cancel_bid(&mut nugget.data, &mut [(pid, &mut player.data)]);
+                            nugget.data.status = NUGGET_CONSUMED;
+                            nugget.store();
+                            NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        }
+                        for slot in slots.iter().rev() {
+                            player.data.inventory.remove(*slot);
+                        }
+                        let mut global = GLOBAL_STATE.0.borrow_mut();
+                        let mut nugget = NuggetInfo::new_object(
+                            NuggetInfo::crafted(global.total, recipe.feature, recipe.attributes, counter),
+                            global.total
+                        );
+                        nugget.data.compute_sysprice();
+                        nugget.store();
+                        NuggetInfo::emit_event(global.total, &nugget.data);
+                        global.total += 1;
+                        drop(global);
+                        player.data.stats.created += 1;
+                        player.data.record_owned(pid, &nugget.data);
+                        player.data.gain_xp(pid, XP_CREATE);
+                        player.data.inventory.push(nugget.data.id);
+                        player.data.check_collection_sets();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::BuyTool(kind) => {
+                        let kind = CONFIG.tools.get(*kind as usize).ok_or(ERROR_INVALID_TOOL)?;
+                        if player.data.tools.len() >= MAX_TOOLS {
+                            return Err(ERROR_TOOL_SLOTS_FULL);
+                        }
+                        player.data.cost_balance(kind.price)?;
+                        let mut global = GLOBAL_STATE.0.borrow_mut();
+                        let tool = ToolInfo::new_object(ToolInfo::new(global.tool_total, kind.kind, kind.durability), global.tool_total);
+                        tool.store();
+                        ToolInfo::emit_event(tool.data.id, &tool.data);
+                        global.tool_total += 1;
+                        player.data.tools.push(tool.data.id);
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Challenge(target, opponent, wager, take_nugget) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        if opponent == pid || GamePlayer::get_from_pid(opponent).is_none() {
+                            return Err(ERROR_INVALID_OPPONENT);
+                        }
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        player.data.cost_balance(*wager)?;
+                        nugget.data.status = NUGGET_DUEL;
+                        let mut global = GLOBAL_STATE.0.borrow_mut();
+                        let challenge = ChallengeInfo {
+                            id: global.challenge_total,
+                            challenger: pid.clone(),
+                            challenger_nugget: nuggetid,
+                            opponent: opponent.clone(),
+                            opponent_nugget: 0,
+                            wager: *wager,
+                            take_nugget: *take_nugget as u64,
+                            expires: counter + CHALLENGE_EXPIRY_TICKS,
+                            status: CHALLENGE_PENDING,
+                            winner: 0,
+                        };
+                        global.challenge_total += 1;
+                        ChallengeInfo::new_object(challenge.clone(), challenge.id).store();
+                        ChallengeInfo::emit_event(challenge.id, &challenge);
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::AcceptChallenge(cid, target) => {
+                        let mut challenge = ChallengeInfo::get_object(*cid).ok_or(ERROR_INVALID_CHALLENGE)?;
+                        if challenge.data.status != CHALLENGE_PENDING {
+                            return Err(ERROR_INVALID_CHALLENGE);
+                        }
+                        if challenge.data.opponent != *pid {
+                            return Err(ERROR_NOT_CHALLENGE_PARTICIPANT);
+                        }
+                        if counter > challenge.data.expires {
+                            return Err(ERROR_CHALLENGE_EXPIRED);
+                        }
+                        let slot = player.data.locate_nugget(target)?;
+                        let mut nugget = NuggetInfo::get_object(player.data.inventory[slot]).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        player.data.cost_balance(challenge.data.wager)?;
+                        let mut challenger = GamePlayer::get_from_pid(&challenge.data.challenger).unwrap();
+                        let mut challenger_nugget = NuggetInfo::get_object(challenge.data.challenger_nugget).unwrap();
+                        challenger_nugget.data.status = NUGGET_ACTIVE;
+                        challenge.data.opponent_nugget = nugget.data.id;
+                        challenge.data.winner = duel_winner(&challenger_nugget.data, &nugget.data, rand[0]);
+                        challenge.data.status = CHALLENGE_RESOLVED;
+                        let (winner, winner_pid, loser, loser_pid, loser_nugget) = if challenge.data.winner == DUEL_CHALLENGER {
+                            (&mut challenger, challenge.data.challenger, &mut *player, *pid, &mut nugget)
                         } else {
-                            let nuggetid = player.data.inventory[*index as usize];
-                            let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
-                            match nugget.data.bid {
-                                None => {
-                                    // sell at system price
-                                    player.data.inc_balance(nugget.data.sysprice);
-                                    nugget.data.cycle = 1;
-                                    player.data.inventory.swap_remove(*index as usize);
-                                    nugget.store();
-                                    player.store();
-                                },
-                                Some (bidder) => {
-                                    player.data.inc_balance(bidder.bidprice);
-                                    let mut last_player= GamePlayer::get_from_pid(&bidder.bidder).unwrap();
-                                    last_player.data.inventory.push(nugget.data.id);
-                                    player.data.inventory.swap_remove(*index as usize);
-                                    nugget.store();
-                                    player.store();
-                                    last_player.store();
+                            (&mut *player, *pid, &mut challenger, challenge.data.challenger, &mut challenger_nugget)
+                        };
+                        winner.data.inc_balance(challenge.data.wager * 2);
+                        if challenge.data.take_nugget != 0 {
+                            let slot = loser.data.inventory.iter().position(|x| *x == loser_nugget.data.id).unwrap();
+                            loser.data.inventory.remove(slot);
+                            if winner.data.push_nugget(loser_nugget.data.id).is_ok() {
+                                // a bid the winner placed on the prize is void once they own it
+                                if loser_nugget.data.bid.map_or(false, |b| b.bidder == winner_pid) {
+                                    cancel_bid(&mut loser_nugget.data, &mut [(&winner_pid, &mut winner.data)]);
                                 }
+                                winner.data.record_owned(&winner_pid, &loser_nugget.data);
+                                winner.data.check_collection_sets();
+                            } else {
+                                // no room for the prize, it is sold to the system for the winner
+                                cancel_bid(&mut loser_nugget.data, &mut [
+                                    (&winner_pid, &mut winner.data),
+                                    (&loser_pid, &mut loser.data),
+                                ]);
+                                winner.data.inc_balance(loser_nugget.data.sale_proceeds(loser_nugget.data.effective_price(counter)));
+                                loser_nugget.data.status = NUGGET_RECYCLED;
                             }
-                            NuggetInfo::emit_event(nugget.data.id, &nugget.data);
-                            Ok(())
                         }
+                        challenge.store();
+                        ChallengeInfo::emit_event(challenge.data.id, &challenge.data);
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        NuggetInfo::emit_event(challenger_nugget.data.id, &challenger_nugget.data);
+                        nugget.store();
+                        challenger_nugget.store();
+                        challenger.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::CancelChallenge(cid) => {
+                        let mut challenge = ChallengeInfo::get_object(*cid).ok_or(ERROR_INVALID_CHALLENGE)?;
+                        if challenge.data.status != CHALLENGE_PENDING {
+                            return Err(ERROR_INVALID_CHALLENGE);
+                        }
+                        if challenge.data.challenger != *pid {
+                            return Err(ERROR_NOT_CHALLENGE_PARTICIPANT);
+                        }
+                        let mut nugget = NuggetInfo::get_object(challenge.data.challenger_nugget).unwrap();
+                        nugget.data.status = NUGGET_ACTIVE;
+                        player.data.refund_balance(challenge.data.wager);
+                        challenge.data.status = CHALLENGE_CANCELLED;
+                        challenge.store();
+                        ChallengeInfo::emit_event(challenge.data.id, &challenge.data);
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        nugget.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::UpgradeInventory => {
+                        player.data.upgrade_inventory()?;
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Store(target) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        player.data.cost_balance(WAREHOUSE_FEE)?;
+                        let mut warehouse = player.data.open_warehouse();
+                        warehouse.data.deposit(nuggetid)?;
+                        player.data.inventory.remove(slot);
+                        nugget.data.status = NUGGET_STORED;
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        WarehouseInfo::emit_event(warehouse.data.id, &warehouse.data);
+                        nugget.store();
+                        warehouse.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::Retrieve(nuggetid) => {
+                        let id = player.data.warehouse.ok_or(ERROR_NUGGET_NOT_STORED)?;
+                        let mut warehouse = WarehouseInfo::get_object(id).unwrap();
+                        warehouse.data.withdraw(*nuggetid)?;
+                        player.data.cost_balance(WAREHOUSE_FEE)?;
+                        player.data.push_nugget(*nuggetid)?;
+                        let mut nugget = NuggetInfo::get_object(*nuggetid).unwrap();
+                        nugget.data.status = NUGGET_ACTIVE;
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        WarehouseInfo::emit_event(warehouse.data.id, &warehouse.data);
+                        nugget.store();
+                        warehouse.store();
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::ClaimDaily => {
+                        player.data.claim_daily(counter)?;
+                        player.store();
+                        Ok(())
+                    },
+
+                    Activity::RegisterSession(session, expires, permissions) => {
+                        // neither an existing player nor the admin key can become a session
+                        if session == pid
+                            || *session == GamePlayer::pkey_to_pid(&ADMIN_PUBKEY)
+                            || GamePlayer::get_from_pid(session).is_some() {
+                            return Err(ERROR_INVALID_SESSION);
+                        }
+                        if *expires <= counter || *expires > counter + MAX_SESSION_TICKS {
+                            return Err(ERROR_INVALID_SESSION);
+                        }
+                        if let Some(existing) = SessionInfo::get(session) {
+                            if existing.owner != *pid {
+                                return Err(ERROR_INVALID_SESSION);
+                            }
+                        }
+                        SessionInfo {
+                            owner: *pid,
+                            expires: *expires,
+                            permissions: *permissions,
+                        }.store(session);
+                        Ok(())
+                    },
+
+                    Activity::RevokeSession(session) => {
+                        match SessionInfo::get(session) {
+                            Some(existing) if existing.owner == *pid => {
+                                SessionInfo::revoke(session);
+                                Ok(())
+                            },
+                            _ => Err(ERROR_INVALID_SESSION),
+                        }
+                    },
+
+                    Activity::Sell(target) => {
+                        let slot = player.data.locate_nugget(target)?;
+                        let nuggetid = player.data.inventory[slot];
+                        let mut nugget = NuggetInfo::get_object(nuggetid).unwrap();
+                        if nugget.data.is_locked() {
+                            return Err(ERROR_NUGGET_LOCKED);
+                        }
+                        // a bid naming the seller can only be one left behind by an earlier
+                        // version that never cleared settled bids, it holds no escrow
+                        if nugget.data.bid.map_or(false, |b| b.bidder == *pid) {
+                            nugget.data.bid = None;
+                        }
+                        // a bidder without room for the nugget gets the bid back and the
+                        // nugget stays with the seller, who can then sell it or wait for bids
+                        if let Some(bidder) = nugget.data.bid {
+                            if !GamePlayer::get_from_pid(&bidder.bidder).unwrap().data.has_inventory_space() {
+                                cancel_bid(&mut nugget.data, &mut [(pid, &mut player.data)]);
+                                nugget.store();
+                                player.store();
+                                NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                                return Ok(());
+                            }
+                        }
+                        match nugget.data.settle_bid() {
+                            None => {
+                                // sell at system price
+                                player.data.inc_balance(nugget.data.sale_proceeds(nugget.data.effective_price(counter)));
+                                nugget.data.status = NUGGET_RECYCLED;
+                                player.data.stats.sales += 1;
+                                player.data.gain_xp(pid, XP_TRADE);
+                                player.data.check_trade_achievements(pid);
+                                player.data.inventory.remove(slot);
+                                nugget.store();
+                                player.store();
+                            },
+                            Some (bidder) => {
+                                let fee = bidder.bidprice * GlobalState::get_trade_fee_percent() / 100;
+                                // the bidder only escrowed the price, a Royalty bonus on top of it
+                                // is drawn from the airdrop pool like other rewards
+                                let bonus = GlobalState::draw_airdrop(nugget.data.sale_proceeds(bidder.bidprice) - bidder.bidprice);
+                                player.data.inc_balance(bidder.bidprice + bonus - fee);
+                                let mut last_player= GamePlayer::get_from_pid(&bidder.bidder).unwrap();
+                                last_player.data.inventory.push(nugget.data.id);
+                                let share = fee * REFERRAL_FEE_PERCENT / 100;
+                                match player.data.referrer {
+                                    None => GLOBAL_STATE.0.borrow_mut().airdrop += fee,
+                                    // the buyer may be the seller's referrer, credit the loaded copy
+                                    Some(referrer) if referrer == bidder.bidder => {
+                                        last_player.data.inc_balance(share);
+                                        GLOBAL_STATE.0.borrow_mut().airdrop += fee - share;
+                                    },
+                                    Some(referrer) => {
+                                        let mut referrer = GamePlayer::get_from_pid(&referrer).unwrap();
+                                        referrer.data.inc_balance(share);
+                                        referrer.store();
+                                        GLOBAL_STATE.0.borrow_mut().airdrop += fee - share;
+                                    },
+                                }
+                                last_player.data.stats.purchases += 1;
+                                last_player.data.record_owned(&bidder.bidder, &nugget.data);
+                                last_player.data.gain_xp(&bidder.bidder, XP_TRADE);
+                                last_player.data.check_trade_achievements(&bidder.bidder);
+                                last_player.data.check_collection_sets();
+                                player.data.stats.sales += 1;
+                                player.data.gain_xp(pid, XP_TRADE);
+                                player.data.check_trade_achievements(pid);
+                                player.data.inventory.remove(slot);
+                                nugget.store();
+                                player.store();
+                                last_player.store();
+                            }
+                        }
+                        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+                        Ok(())
                     },
 
                     Activity::Bid(nid, price) => {
                         player.data.cost_balance(*price)?;
                         let nugget = NuggetInfo::get_object(*nid);
                         match nugget {
+                            Some(ref n) if n.data.is_locked() => Err(ERROR_NUGGET_LOCKED),
+                            // selling to oneself would hand the escrow and the nugget back
+                            Some(_) if player.data.inventory.contains(nid) => Err(ERROR_BID_ON_OWN_NUGGET),
                             Some(mut n) => {
-                                match n.data.bid {
-                                    Some(bidder) => {
-                                        if bidder.bidprice >= *price {
-                                            Err(ERROR_BID_PRICE_INSUFFICIENT)
-                                        } else {
-                                            let mut last_player= GamePlayer::get_from_pid(&bidder.bidder).unwrap();
-                                            last_player.data.inc_balance(bidder.bidprice);
-                                            n.data.bid = Some(BidInfo {
-                                                bidprice: *price,
-                                                bidder: pid.clone(),
-                                            });
-                                            last_player.store();
-                                            n.store();
-                                            NuggetInfo::emit_event(n.data.id, &n.data);
-                                            Ok(())
-                                        }
-                                    },
-                                    None => {
-                                        n.data.bid = Some(BidInfo {
-                                            bidprice: *price,
-                                            bidder: pid.clone(),
-                                        });
-                                        n.store();
-                                        NuggetInfo::emit_event(n.data.id, &n.data);
-                                        Ok(())
-                                    }
+                                if let Some(last) = n.data.place_bid(pid, *price)? {
+                                    // the previous bidder may be the player raising their own bid
+                                    refund_bid(&last, &mut [(pid, &mut player.data)]);
                                 }
+                                player.store();
+                                n.store();
+                                NuggetInfo::emit_event(n.data.id, &n.data);
+                                Ok(())
                             },
                             None => Err(INVALID_NUGGET_INDEX)
                         }
@@ -194,6 +793,28 @@ impl CommandHandler for Activity {
     }
 }
 
+/// Gives the escrow of `bid` back to its bidder. A bidder among the `loaded`
+/// players is credited on that copy so that storing it afterwards keeps the
+/// refund.
+fn refund_bid(bid: &BidInfo, loaded: &mut [(&[u64; 2], &mut PlayerData)]) {
+    match loaded.iter_mut().find(|(owner, _)| **owner == bid.bidder) {
+        Some((_, player)) => player.refund_balance(bid.bidprice),
+        None => {
+            let mut bidder = GamePlayer::get_from_pid(&bid.bidder).unwrap();
+            bidder.data.refund_balance(bid.bidprice);
+            bidder.store();
+        }
+    }
+}
+
+// refunds and clears the pending bid of a nugget that leaves the market
+// without being sold to its bidder
+fn cancel_bid(nugget: &mut NuggetInfo, loaded: &mut [(&[u64; 2], &mut PlayerData)]) {
+    if let Some(bid) = nugget.bid.take() {
+        refund_bid(&bid, loaded);
+    }
+}
+
 pub fn decode_error(e: u32) -> &'static str {
     match e {
         ERROR_PLAYER_NOT_EXIST => "PlayerNotExist",
@@ -205,6 +826,38 @@ pub fn decode_error(e: u32) -> &'static str {
         PLAYER_NOT_ENOUGH_INVENTORY=> "PlayerInventoryFull",
         ERROR_BID_PRICE_INSUFFICIENT => "BidPriceInsufficient",
         ERROR_NUGGET_ATTRIBUTES_ALL_EXPLORED => "NuggetAttributeAllExplored",
+        ERROR_NUGGET_NOT_FULLY_EXPLORED => "NuggetNotFullyExplored",
+        INVALID_ATTRIBUTE_INDEX => "InvalidAttributeIndex",
+        ERROR_ATTRIBUTE_NOT_EXPLORED => "AttributeNotExplored",
+        ERROR_NUGGET_LOCKED => "NuggetLocked",
+        ERROR_NUGGET_NOT_STAKED => "NuggetNotStaked",
+        NOTHING_TO_COLLECT => "NothingToCollect",
+        ERROR_INVALID_EXPEDITION_DURATION => "InvalidExpeditionDuration",
+        ERROR_EXPEDITION_SCHEDULE_FULL => "ExpeditionScheduleFull",
+        ERROR_INVALID_RECIPE => "InvalidRecipe",
+        ERROR_RECIPE_REQUIREMENT_NOT_MET => "RecipeRequirementNotMet",
+        ERROR_INVALID_TOOL => "InvalidTool",
+        ERROR_TOOL_SLOTS_FULL => "ToolSlotsFull",
+        ERROR_NUGGET_SUPPLY_EXHAUSTED => "NuggetSupplyExhausted",
+        ERROR_CREATE_COOLDOWN => "CreateCooldown",
+        ERROR_INVALID_OPPONENT => "InvalidOpponent",
+        ERROR_INVALID_CHALLENGE => "InvalidChallenge",
+        ERROR_CHALLENGE_EXPIRED => "ChallengeExpired",
+        ERROR_NOT_CHALLENGE_PARTICIPANT => "NotChallengeParticipant",
+        ERROR_INVENTORY_SIZE_CAP => "InventorySizeCapReached",
+        ERROR_NUGGET_NOT_OWNED => "NuggetNotOwned",
+        ERROR_WAREHOUSE_FULL => "WarehouseFull",
+        ERROR_NUGGET_NOT_STORED => "NuggetNotStored",
+        ERROR_INVALID_REFERRER => "InvalidReferrer",
+        ERROR_CLAIM_NOT_READY => "ClaimNotReady",
+        ERROR_INVALID_SESSION => "InvalidSession",
+        ERROR_SESSION_EXPIRED => "SessionExpired",
+        ERROR_SESSION_NOT_PERMITTED => "SessionNotPermitted",
+        ERROR_PLAYER_FROZEN => "PlayerFrozen",
+        ERROR_INVALID_TRADE_FEE => "InvalidTradeFee",
+        ERROR_INVALID_EXPLORE_COST => "InvalidExploreCost",
+        ERROR_INVALID_CREATION_CONFIG => "InvalidCreationConfig",
+        ERROR_BID_ON_OWN_NUGGET => "BidOnOwnNugget",
         _ => "Unknown",
     }
 }
@@ -1,5 +1,8 @@
 use serde::Serialize;
 
+use crate::nugget::*;
+use crate::tool::*;
+use crate::achievement::*;
 use crate::state::GLOBAL_STATE;
 
 lazy_static::lazy_static! {
@@ -11,23 +14,377 @@ lazy_static::lazy_static! {
     };
 }
 
+// consecutive explores revealing nothing above PITY_LOW_ATTRIBUTE
+// before the next explore is guaranteed a top roll
+pub const PITY_THRESHOLD: u64 = 5;
+pub const PITY_LOW_ATTRIBUTE: u8 = 4;
+
+// a nugget loses DECAY_PERCENT of its price every DECAY_PERIOD ticks
+// without being explored or maintained, setting the period to 0 disables decay
+pub const DECAY_PERIOD: u64 = 1000;
+pub const DECAY_PERCENT: u64 = 5;
+pub const MAX_DECAY_PERCENT: u64 = 50;
+pub const MAINTAIN_BASE_FEE: u64 = 10;
+
+// a staked nugget yields sysprice / STAKE_YIELD_DIVISOR per tick
+pub const STAKE_YIELD_DIVISOR: u64 = 1000;
+
+// expeditions lock a nugget for a bounded number of ticks
+pub const MIN_EXPEDITION_TICKS: u64 = 10;
+pub const MAX_EXPEDITION_TICKS: u64 = 10000;
+pub const EXPEDITION_FEE: u64 = 20;
+pub const EXPEDITION_BALANCE_PER_TICK: u64 = 1;
+// bound the work done by a single admin tick and the pending queue
+pub const MAX_EXPEDITIONS_PER_TICK: usize = 8;
+pub const MAX_PENDING_EXPEDITIONS: u64 = 100000;
+
+/// Explore cost curve, kept in the global state so the admin can adjust it.
+/// The cost is `base + per_attribute * explored + sysprice * percent / 100`,
+/// capped at `cap` and scaled by the refine cycle.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct ExploreCost {
+    pub base: u64,
+    pub per_attribute: u64,
+    pub percent: u64,
+    pub cap: u64,
+}
+
+// highest sysprice percent the admin can put into the explore cost
+pub const MAX_EXPLORE_COST_PERCENT: u64 = 1000;
+
+impl ExploreCost {
+    // a curve that can reach zero would make exploring free again
+    pub fn is_valid(&self) -> bool {
+        self.base > 0 && self.cap >= self.base && self.percent <= MAX_EXPLORE_COST_PERCENT
+    }
+}
+
+pub const DEFAULT_EXPLORE_COST: ExploreCost = ExploreCost {
+    base: 10,
+    per_attribute: 5,
+    percent: 25,
+    cap: 5000,
+};
+
+/// Nugget creation controls, also adjustable by the admin. The fee grows by
+/// `fee_step` for every hundred nuggets minted so far.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct CreationConfig {
+    pub fee: u64,
+    pub fee_step: u64,
+    pub supply_cap: u64,
+    pub cooldown: u64,
+}
+
+pub const DEFAULT_CREATION_CONFIG: CreationConfig = CreationConfig {
+    fee: 50,
+    fee_step: 1,
+    supply_cap: 100000,
+    cooldown: 10,
+};
+
+impl CreationConfig {
+    pub fn creation_fee(&self, total: u64) -> u64 {
+        self.fee + total / 100 * self.fee_step
+    }
+}
+
+// insured explores pay a premium into the insurance pool and get part of
+// the explore cost back when nothing above the threshold is revealed, at
+// worst about 61% of explores are refunded so the expected payout of about
+// 18% of the cost stays below the premium
+pub const INSURANCE_PREMIUM_PERCENT: u64 = 20;
+pub const INSURANCE_REFUND_PERCENT: u64 = 30;
+pub const INSURANCE_THRESHOLD: u8 = 4;
+
+// ticks a duel challenge stays open for the opponent to accept
+pub const CHALLENGE_EXPIRY_TICKS: u64 = 1000;
+
+// inventory upgrades add one slot each, the price doubles with every
+// slot bought beyond the default size
+pub const DEFAULT_INVENTORY_SIZE: u64 = 4;
+pub const MAX_INVENTORY_SIZE: u64 = 12;
+pub const INVENTORY_UPGRADE_BASE_PRICE: u64 = 500;
+
+// the warehouse holds nuggets outside the inventory, moving a nugget in
+// or out of it costs WAREHOUSE_FEE
+pub const WAREHOUSE_CAPACITY: usize = 64;
+pub const WAREHOUSE_FEE: u64 = 5;
+
+// installing with a referrer draws REFERRAL_BONUS from the airdrop pool,
+// REFERRER_SHARE_PERCENT of it goes to the referrer and the rest to the
+// new player
+pub const REFERRAL_BONUS: u64 = 200;
+pub const REFERRER_SHARE_PERCENT: u64 = 50;
+// bid sales pay the admin configured trade fee percent of the price back into
+// the airdrop pool, the seller's referrer receives REFERRAL_FEE_PERCENT of
+// that fee. No fee is taken until the admin sets one.
+pub const DEFAULT_TRADE_FEE_PERCENT: u64 = 0;
+pub const REFERRAL_FEE_PERCENT: u64 = 20;
+
+// the daily reward can be claimed once every DAILY_WINDOW ticks, claiming
+// again within the following window keeps the streak going and every
+// streak day up to MAX_DAILY_STREAK adds DAILY_STREAK_STEP to the reward
+pub const DAILY_WINDOW: u64 = 1000;
+pub const DAILY_BASE_REWARD: u64 = 20;
+pub const DAILY_STREAK_STEP: u64 = 10;
+pub const MAX_DAILY_STREAK: u64 = 7;
+
+// longest a session key can stay valid after it is registered
+pub const MAX_SESSION_TICKS: u64 = 100000;
+
+#[derive(Serialize, Clone)]
+pub struct FeatureEffect {
+    feature: u64,
+    name: &'static str,
+    effect: &'static str,
+}
+
+// tools a player can carry at once
+pub const MAX_TOOLS: usize = 4;
+
+#[derive(Serialize, Clone)]
+pub struct ToolKind {
+    pub kind: u64,
+    pub name: &'static str,
+    pub price: u64,
+    pub durability: u64,
+    pub effect: &'static str,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RecipeInput {
+    pub feature: Option<u64>,
+    pub min_rarity: u64,
+}
+
+impl RecipeInput {
+    pub fn accepts(&self, nugget: &NuggetInfo) -> bool {
+        self.feature.map_or(true, |f| f == nugget.feature) && nugget.rarity() >= self.min_rarity
+    }
+}
+
+/// Consumes one nugget per input, matched in order, plus the fee and
+/// produces a nugget with the given feature and guaranteed attributes.
+#[derive(Serialize, Clone)]
+pub struct Recipe {
+    pub id: u64,
+    pub name: &'static str,
+    pub inputs: Vec<RecipeInput>,
+    pub fee: u64,
+    pub feature: u64,
+    pub attributes: [u8; 8],
+}
+
+/// Holding one nugget of each listed feature completes the set and pays
+/// its one-off reward from the airdrop pool.
+#[derive(Serialize, Clone)]
+pub struct CollectionSet {
+    pub id: u64,
+    pub name: &'static str,
+    pub features: Vec<u64>,
+    pub reward: u64,
+}
+
+// experience gained per action, trades count for both seller and buyer
+pub const XP_EXPLORE: u64 = 10;
+pub const XP_CREATE: u64 = 20;
+pub const XP_TRADE: u64 = 25;
+
+/// Reaching `xp` experience unlocks the level. The perks of a level are its
+/// full bonus, they do not add up with the levels below it.
+#[derive(Serialize, Clone)]
+pub struct Level {
+    pub level: u64,
+    pub xp: u64,
+    pub inventory_bonus: u64,
+    pub explore_discount: u64,
+}
+
+/// One-off milestone, unlocking it pays `reward` from the airdrop pool.
+#[derive(Serialize, Clone)]
+pub struct Achievement {
+    pub id: u64,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub reward: u64,
+}
+
+impl CollectionSet {
+    pub fn feature_mask(&self) -> u64 {
+        self.features.iter().fold(0, |mask, f| mask | (1u64 << *f))
+    }
+}
+
 #[derive(Serialize, Clone)]
 pub struct Config {
     actions: [&'static str; 1],
     name: [&'static str; 1],
+    features: [FeatureEffect; 8],
+    pity_threshold: u64,
+    pity_low_attribute: u8,
+    decay_period: u64,
+    decay_percent: u64,
+    max_decay_percent: u64,
+    maintain_base_fee: u64,
+    stake_yield_divisor: u64,
+    min_expedition_ticks: u64,
+    max_expedition_ticks: u64,
+    expedition_fee: u64,
+    pub recipes: Vec<Recipe>,
+    pub tools: Vec<ToolKind>,
+    max_tools: usize,
+    insurance_premium_percent: u64,
+    insurance_refund_percent: u64,
+    insurance_threshold: u8,
+    pub sets: Vec<CollectionSet>,
+    challenge_expiry_ticks: u64,
+    default_inventory_size: u64,
+    max_inventory_size: u64,
+    inventory_upgrade_base_price: u64,
+    warehouse_capacity: usize,
+    warehouse_fee: u64,
+    referral_bonus: u64,
+    referrer_share_percent: u64,
+    referral_fee_percent: u64,
+    daily_window: u64,
+    daily_base_reward: u64,
+    daily_streak_step: u64,
+    max_daily_streak: u64,
+    max_session_ticks: u64,
+    xp_explore: u64,
+    xp_create: u64,
+    xp_trade: u64,
+    pub levels: Vec<Level>,
+    pub achievements: Vec<Achievement>,
 }
 
 lazy_static::lazy_static! {
     pub static ref CONFIG: Config = Config {
         actions: ["nugget"],
         name: ["nugget"],
+        features: [
+            FeatureEffect { feature: FEATURE_PLAIN, name: "Plain", effect: "no special effect" },
+            FeatureEffect { feature: FEATURE_CHEAP_DRILL, name: "Cheap Drill", effect: "exploring costs half" },
+            FeatureEffect { feature: FEATURE_DEEP_VEIN, name: "Deep Vein", effect: "explored attributes roll up to 10" },
+            FeatureEffect { feature: FEATURE_ROYALTY, name: "Royalty", effect: "sales pay an extra 10%" },
+            FeatureEffect { feature: FEATURE_REFINERY, name: "Refinery", effect: "refining costs half" },
+            FeatureEffect { feature: FEATURE_STEADY, name: "Steady", effect: "explored attributes never roll below 3" },
+            FeatureEffect { feature: FEATURE_OVERCLOCK, name: "Overclock", effect: "refine cycles raise the price multiplier twice as fast" },
+            FeatureEffect { feature: FEATURE_LUCKY, name: "Lucky", effect: "exploring rolls twice and keeps the higher" },
+        ],
+        pity_threshold: PITY_THRESHOLD,
+        pity_low_attribute: PITY_LOW_ATTRIBUTE,
+        decay_period: DECAY_PERIOD,
+        decay_percent: DECAY_PERCENT,
+        max_decay_percent: MAX_DECAY_PERCENT,
+        maintain_base_fee: MAINTAIN_BASE_FEE,
+        stake_yield_divisor: STAKE_YIELD_DIVISOR,
+        min_expedition_ticks: MIN_EXPEDITION_TICKS,
+        max_expedition_ticks: MAX_EXPEDITION_TICKS,
+        expedition_fee: EXPEDITION_FEE,
+        recipes: vec![
+            Recipe {
+                id: 0,
+                name: "Fused Core",
+                inputs: vec![
+                    RecipeInput { feature: None, min_rarity: RARITY_COMMON },
+                    RecipeInput { feature: None, min_rarity: RARITY_COMMON },
+                ],
+                fee: 100,
+                feature: FEATURE_PLAIN,
+                attributes: [5, 5, 5, 0, 0, 0, 0, 0],
+            },
+            Recipe {
+                id: 1,
+                name: "Prism",
+                inputs: vec![
+                    RecipeInput { feature: Some(FEATURE_DEEP_VEIN), min_rarity: RARITY_RARE },
+                    RecipeInput { feature: Some(FEATURE_STEADY), min_rarity: RARITY_RARE },
+                    RecipeInput { feature: Some(FEATURE_LUCKY), min_rarity: RARITY_RARE },
+                ],
+                fee: 500,
+                feature: FEATURE_DEEP_VEIN,
+                attributes: [9, 9, 9, 9, 0, 0, 0, 0],
+            },
+            Recipe {
+                id: 2,
+                name: "Royal Seal",
+                inputs: vec![
+                    RecipeInput { feature: Some(FEATURE_ROYALTY), min_rarity: RARITY_EPIC },
+                    RecipeInput { feature: Some(FEATURE_ROYALTY), min_rarity: RARITY_EPIC },
+                ],
+                fee: 1000,
+                feature: FEATURE_ROYALTY,
+                attributes: [9, 9, 9, 9, 9, 9, 0, 0],
+            },
+        ],
+        tools: vec![
+            ToolKind { kind: TOOL_DRILL, name: "Drill", price: 200, durability: 5, effect: "exploring costs half" },
+            ToolKind { kind: TOOL_SCANNER, name: "Scanner", price: 300, durability: 3, effect: "exploring never fails or collapses" },
+        ],
+        max_tools: MAX_TOOLS,
+        insurance_premium_percent: INSURANCE_PREMIUM_PERCENT,
+        insurance_refund_percent: INSURANCE_REFUND_PERCENT,
+        insurance_threshold: INSURANCE_THRESHOLD,
+        sets: vec![
+            CollectionSet {
+                id: 0,
+                name: "Lower Octave",
+                features: vec![FEATURE_PLAIN, FEATURE_CHEAP_DRILL, FEATURE_DEEP_VEIN, FEATURE_ROYALTY],
+                reward: 500,
+            },
+            CollectionSet {
+                id: 1,
+                name: "Upper Octave",
+                features: vec![FEATURE_REFINERY, FEATURE_STEADY, FEATURE_OVERCLOCK, FEATURE_LUCKY],
+                reward: 500,
+            },
+            CollectionSet {
+                id: 2,
+                name: "Full Spectrum",
+                features: (0..8).collect(),
+                reward: 5000,
+            },
+        ],
+        challenge_expiry_ticks: CHALLENGE_EXPIRY_TICKS,
+        default_inventory_size: DEFAULT_INVENTORY_SIZE,
+        max_inventory_size: MAX_INVENTORY_SIZE,
+        inventory_upgrade_base_price: INVENTORY_UPGRADE_BASE_PRICE,
+        warehouse_capacity: WAREHOUSE_CAPACITY,
+        warehouse_fee: WAREHOUSE_FEE,
+        referral_bonus: REFERRAL_BONUS,
+        referrer_share_percent: REFERRER_SHARE_PERCENT,
+        referral_fee_percent: REFERRAL_FEE_PERCENT,
+        daily_window: DAILY_WINDOW,
+        daily_base_reward: DAILY_BASE_REWARD,
+        daily_streak_step: DAILY_STREAK_STEP,
+        max_daily_streak: MAX_DAILY_STREAK,
+        max_session_ticks: MAX_SESSION_TICKS,
+        xp_explore: XP_EXPLORE,
+        xp_create: XP_CREATE,
+        xp_trade: XP_TRADE,
+        levels: vec![
+            Level { level: 0, xp: 0, inventory_bonus: 0, explore_discount: 0 },
+            Level { level: 1, xp: 100, inventory_bonus: 0, explore_discount: 5 },
+            Level { level: 2, xp: 300, inventory_bonus: 1, explore_discount: 5 },
+            Level { level: 3, xp: 700, inventory_bonus: 1, explore_discount: 10 },
+            Level { level: 4, xp: 1500, inventory_bonus: 2, explore_discount: 10 },
+            Level { level: 5, xp: 3000, inventory_bonus: 2, explore_discount: 15 },
+            Level { level: 6, xp: 6000, inventory_bonus: 3, explore_discount: 20 },
+        ],
+        achievements: vec![
+            Achievement { id: ACHIEVEMENT_FIRST_SALE, name: "First Sale", description: "sell a nugget", reward: 50 },
+            Achievement { id: ACHIEVEMENT_FULLY_EXPLORED, name: "Cartographer", description: "fully explore a nugget", reward: 100 },
+            Achievement { id: ACHIEVEMENT_TEN_TRADES, name: "Trader", description: "complete 10 sales or purchases", reward: 200 },
+            Achievement { id: ACHIEVEMENT_LEGENDARY, name: "Legend", description: "own a legendary nugget", reward: 500 },
+        ],
     };
 }
 
 impl Config {
     pub fn to_json_string() -> String {
-        let meme_list: Vec<u64> = vec![];
-        serde_json::to_string(&meme_list).unwrap()
+        serde_json::to_string(&CONFIG.clone()).unwrap()
     }
 
     // enable timer tick
@@ -0,0 +1,96 @@
+use core::slice::IterMut;
+use serde::Serialize;
+use zkwasm_rest_abi::StorageData;
+use zkwasm_rest_convention::IndexedObject;
+use crate::nugget::NuggetInfo;
+
+// challenge status
+pub const CHALLENGE_PENDING: u64 = 0;
+pub const CHALLENGE_RESOLVED: u64 = 1;
+pub const CHALLENGE_CANCELLED: u64 = 2;
+
+// duel winner
+pub const DUEL_CHALLENGER: u64 = 0;
+pub const DUEL_OPPONENT: u64 = 1;
+
+/// A pending duel. The challenger's nugget and wager are locked until the
+/// opponent accepts before `expires` or the challenger cancels.
+#[derive(Clone, Serialize, Default)]
+pub struct ChallengeInfo {
+    pub id: u64,
+    pub challenger: [u64; 2],
+    pub challenger_nugget: u64,
+    pub opponent: [u64; 2],
+    pub opponent_nugget: u64,
+    pub wager: u64,
+    pub take_nugget: u64,
+    pub expires: u64,
+    pub status: u64,
+    pub winner: u64,
+}
+
+impl StorageData for ChallengeInfo {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let id = *u64data.next().unwrap();
+        let challenger = [*u64data.next().unwrap(), *u64data.next().unwrap()];
+        let challenger_nugget = *u64data.next().unwrap();
+        let opponent = [*u64data.next().unwrap(), *u64data.next().unwrap()];
+        let opponent_nugget = *u64data.next().unwrap();
+        let wager = *u64data.next().unwrap();
+        let take_nugget = *u64data.next().unwrap();
+        let expires = *u64data.next().unwrap();
+        let status = *u64data.next().unwrap();
+        let winner = *u64data.next().unwrap();
+        ChallengeInfo {
+            id,
+            challenger,
+            challenger_nugget,
+            opponent,
+            opponent_nugget,
+            wager,
+            take_nugget,
+            expires,
+            status,
+            winner,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.id);
+        data.push(self.challenger[0]);
+        data.push(self.challenger[1]);
+        data.push(self.challenger_nugget);
+        data.push(self.opponent[0]);
+        data.push(self.opponent[1]);
+        data.push(self.opponent_nugget);
+        data.push(self.wager);
+        data.push(self.take_nugget);
+        data.push(self.expires);
+        data.push(self.status);
+        data.push(self.winner);
+    }
+}
+
+impl IndexedObject<ChallengeInfo> for ChallengeInfo {
+    const PREFIX: u64 = 0x1ee6;
+    const POSTFIX: u64 = 0xfee6;
+    const EVENT_NAME: u64 = 0x07;
+}
+
+// attributes count on their normalized value so the raw seed byte does not
+// decide the duel
+fn duel_power(nugget: &NuggetInfo) -> u64 {
+    let sum: u64 = nugget.attributes.iter().map(|c| NuggetInfo::attribute_value(*c)).sum();
+    sum * (nugget.cycle + 1) + nugget.feature
+}
+
+/// Decides a duel from both nuggets and the transaction randomness, each
+/// side adds a luck roll of up to 19 to its power. Ties go to the challenger.
+pub fn duel_winner(challenger: &NuggetInfo, opponent: &NuggetInfo, rand: u64) -> u64 {
+    let challenger_score = duel_power(challenger) + rand % 20;
+    let opponent_score = duel_power(opponent) + (rand >> 8) % 20;
+    if opponent_score > challenger_score {
+        DUEL_OPPONENT
+    } else {
+        DUEL_CHALLENGER
+    }
+}
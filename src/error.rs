@@ -8,5 +8,36 @@ pub const PLAYER_NOT_ENOUGH_INVENTORY: u32 = 7;
 pub const PLAYER_NOT_ENOUGH_BALANCE: u32 = 8;
 pub const INVALID_NUGGET_INDEX: u32 = 9;
 pub const NOTHING_TO_COLLECT: u32 = 10;
+pub const ERROR_NUGGET_NOT_FULLY_EXPLORED: u32 = 11;
+pub const INVALID_ATTRIBUTE_INDEX: u32 = 12;
+pub const ERROR_ATTRIBUTE_NOT_EXPLORED: u32 = 13;
+pub const ERROR_NUGGET_LOCKED: u32 = 14;
+pub const ERROR_NUGGET_NOT_STAKED: u32 = 15;
+pub const ERROR_INVALID_EXPEDITION_DURATION: u32 = 16;
+pub const ERROR_EXPEDITION_SCHEDULE_FULL: u32 = 17;
+pub const ERROR_INVALID_RECIPE: u32 = 18;
+pub const ERROR_RECIPE_REQUIREMENT_NOT_MET: u32 = 19;
+pub const ERROR_INVALID_TOOL: u32 = 20;
+pub const ERROR_TOOL_SLOTS_FULL: u32 = 21;
+pub const ERROR_NUGGET_SUPPLY_EXHAUSTED: u32 = 22;
+pub const ERROR_CREATE_COOLDOWN: u32 = 23;
+pub const ERROR_INVALID_OPPONENT: u32 = 24;
+pub const ERROR_INVALID_CHALLENGE: u32 = 25;
+pub const ERROR_CHALLENGE_EXPIRED: u32 = 26;
+pub const ERROR_NOT_CHALLENGE_PARTICIPANT: u32 = 27;
+pub const ERROR_INVENTORY_SIZE_CAP: u32 = 28;
+pub const ERROR_NUGGET_NOT_OWNED: u32 = 29;
+pub const ERROR_WAREHOUSE_FULL: u32 = 30;
+pub const ERROR_NUGGET_NOT_STORED: u32 = 31;
+pub const ERROR_INVALID_REFERRER: u32 = 32;
+pub const ERROR_CLAIM_NOT_READY: u32 = 33;
+pub const ERROR_INVALID_SESSION: u32 = 34;
+pub const ERROR_SESSION_EXPIRED: u32 = 35;
+pub const ERROR_SESSION_NOT_PERMITTED: u32 = 36;
+pub const ERROR_PLAYER_FROZEN: u32 = 37;
+pub const ERROR_INVALID_TRADE_FEE: u32 = 38;
+pub const ERROR_INVALID_EXPLORE_COST: u32 = 39;
+pub const ERROR_INVALID_CREATION_CONFIG: u32 = 40;
+pub const ERROR_BID_ON_OWN_NUGGET: u32 = 41;
 
 
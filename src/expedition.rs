@@ -0,0 +1,261 @@
+use core::slice::IterMut;
+use serde::Serialize;
+use zkwasm_rest_abi::StorageData;
+use zkwasm_rest_convention::IndexedObject;
+use crate::config::{EXPEDITION_BALANCE_PER_TICK, MAX_EXPEDITIONS_PER_TICK, MAX_PENDING_EXPEDITIONS};
+use crate::error::*;
+use crate::nugget::{NuggetInfo, NUGGET_ACTIVE};
+use crate::player::GamePlayer;
+use crate::state::GLOBAL_STATE;
+
+// expedition status
+pub const EXPEDITION_ONGOING: u64 = 0;
+pub const EXPEDITION_FINISHED: u64 = 1;
+
+// expedition rewards
+pub const REWARD_BALANCE: u64 = 0;
+pub const REWARD_REVEAL: u64 = 1;
+pub const REWARD_NUGGET: u64 = 2;
+
+#[derive(Clone, Serialize, Default)]
+pub struct ExpeditionInfo {
+    pub id: u64,
+    pub owner: [u64; 2],
+    pub nugget: u64,
+    pub start: u64,
+    pub end: u64,
+    pub status: u64,
+    pub reward: u64,
+    pub amount: u64,
+}
+
+impl StorageData for ExpeditionInfo {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let id = *u64data.next().unwrap();
+        let owner = [*u64data.next().unwrap(), *u64data.next().unwrap()];
+        let nugget = *u64data.next().unwrap();
+        let start = *u64data.next().unwrap();
+        let end = *u64data.next().unwrap();
+        let status = *u64data.next().unwrap();
+        let reward = *u64data.next().unwrap();
+        let amount = *u64data.next().unwrap();
+        ExpeditionInfo {
+            id,
+            owner,
+            nugget,
+            start,
+            end,
+            status,
+            reward,
+            amount,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.id);
+        data.push(self.owner[0]);
+        data.push(self.owner[1]);
+        data.push(self.nugget);
+        data.push(self.start);
+        data.push(self.end);
+        data.push(self.status);
+        data.push(self.reward);
+        data.push(self.amount);
+    }
+}
+
+impl IndexedObject<ExpeditionInfo> for ExpeditionInfo {
+    const PREFIX: u64 = 0x1ee3;
+    const POSTFIX: u64 = 0xfee3;
+    const EVENT_NAME: u64 = 0x04;
+}
+
+/// Slot of the pending expedition queue, a min-heap on the end tick stored
+/// one entry per slot so that pushing and popping touch O(log n) entries.
+#[derive(Clone, Serialize, Default, Copy)]
+pub struct QueueEntry {
+    pub end: u64,
+    pub expedition: u64,
+}
+
+impl StorageData for QueueEntry {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let end = *u64data.next().unwrap();
+        let expedition = *u64data.next().unwrap();
+        QueueEntry {
+            end,
+            expedition,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.end);
+        data.push(self.expedition);
+    }
+}
+
+impl IndexedObject<QueueEntry> for QueueEntry {
+    const PREFIX: u64 = 0x1ee4;
+    const POSTFIX: u64 = 0xfee4;
+    const EVENT_NAME: u64 = 0x05;
+}
+
+impl QueueEntry {
+    fn get(slot: u64) -> Self {
+        QueueEntry::get_object(slot).unwrap().data
+    }
+
+    fn set(slot: u64, entry: QueueEntry) {
+        QueueEntry::new_object(entry, slot).store();
+    }
+
+    fn push(entry: QueueEntry) -> Result<(), u32> {
+        let len = GLOBAL_STATE.0.borrow().expedition_queue;
+        if len >= MAX_PENDING_EXPEDITIONS {
+            return Err(ERROR_EXPEDITION_SCHEDULE_FULL);
+        }
+        let mut slot = len;
+        while slot > 0 {
+            let parent = (slot - 1) / 2;
+            let p = QueueEntry::get(parent);
+            if p.end <= entry.end {
+                break;
+            }
+            QueueEntry::set(slot, p);
+            slot = parent;
+        }
+        QueueEntry::set(slot, entry);
+        GLOBAL_STATE.0.borrow_mut().expedition_queue = len + 1;
+        Ok(())
+    }
+
+    // the expedition ending first, if it has ended by counter
+    fn pop_due(counter: u64) -> Option<Self> {
+        let len = GLOBAL_STATE.0.borrow().expedition_queue;
+        if len == 0 {
+            return None;
+        }
+        let top = QueueEntry::get(0);
+        if top.end > counter {
+            return None;
+        }
+        let len = len - 1;
+        let last = QueueEntry::get(len);
+        let mut slot = 0;
+        loop {
+            let mut child = slot * 2 + 1;
+            if child >= len {
+                break;
+            }
+            let mut c = QueueEntry::get(child);
+            if child + 1 < len {
+                let right = QueueEntry::get(child + 1);
+                if right.end < c.end {
+                    child += 1;
+                    c = right;
+                }
+            }
+            if last.end <= c.end {
+                break;
+            }
+            QueueEntry::set(slot, c);
+            slot = child;
+        }
+        if len > 0 {
+            QueueEntry::set(slot, last);
+        }
+        GLOBAL_STATE.0.borrow_mut().expedition_queue = len;
+        Some(top)
+    }
+}
+
+impl ExpeditionInfo {
+    /// Schedules a new expedition for `nugget`, the caller is responsible for
+    /// locking the nugget.
+    pub fn start(owner: &[u64; 2], nugget: u64, counter: u64, duration: u64) -> Result<Self, u32> {
+        let end = counter + duration;
+        let id = GLOBAL_STATE.0.borrow().expedition_total;
+        QueueEntry::push(QueueEntry { end, expedition: id })?;
+        GLOBAL_STATE.0.borrow_mut().expedition_total += 1;
+        let expedition = ExpeditionInfo {
+            id,
+            owner: owner.clone(),
+            nugget,
+            start: counter,
+            end,
+            status: EXPEDITION_ONGOING,
+            reward: 0,
+            amount: 0,
+        };
+        ExpeditionInfo::new_object(expedition.clone(), expedition.id).store();
+        ExpeditionInfo::emit_event(expedition.id, &expedition);
+        Ok(expedition)
+    }
+
+    fn resolve(&mut self, rand: u64, counter: u64) {
+        let mut player = GamePlayer::get_from_pid(&self.owner).unwrap();
+        let mut nugget = NuggetInfo::get_object(self.nugget).unwrap();
+        nugget.data.status = NUGGET_ACTIVE;
+        self.reward = match rand % 100 {
+            0..=59 => REWARD_BALANCE,
+            60..=89 => REWARD_REVEAL,
+            _ => REWARD_NUGGET,
+        };
+        if self.reward == REWARD_REVEAL {
+            match nugget.data.reveal(rand >> 8) {
+                Some(slot) => {
+                    self.amount = slot as u64;
+                    nugget.data.maintained = counter;
+                    nugget.data.compute_sysprice();
+                    player.data.record_owned(&self.owner, &nugget.data);
+                },
+                None => self.reward = REWARD_BALANCE,
+            }
+        }
+        if self.reward == REWARD_NUGGET {
+            let mut global = GLOBAL_STATE.0.borrow_mut();
+            if player.data.has_inventory_space() && global.total < global.creation.supply_cap {
+                let mut found = NuggetInfo::new_object(NuggetInfo::new(global.total, rand >> 8, counter), global.total);
+                found.data.compute_sysprice();
+                found.store();
+                NuggetInfo::emit_event(global.total, &found.data);
+                player.data.inventory.push(found.data.id);
+                self.amount = found.data.id;
+                global.total += 1;
+                // record_owned may draw an achievement reward from the global state
+                drop(global);
+                player.data.record_owned(&self.owner, &found.data);
+            } else {
+                self.reward = REWARD_BALANCE;
+            }
+        }
+        if self.reward == REWARD_NUGGET {
+            player.data.check_collection_sets();
+        }
+        if self.reward == REWARD_BALANCE {
+            self.amount = (self.end - self.start) * EXPEDITION_BALANCE_PER_TICK;
+            player.data.inc_balance(self.amount);
+        }
+        self.status = EXPEDITION_FINISHED;
+        NuggetInfo::emit_event(nugget.data.id, &nugget.data);
+        nugget.store();
+        player.store();
+    }
+}
+
+/// Resolves the expeditions whose end tick has passed, earliest first.
+/// Called on the admin tick, resolving at most MAX_EXPEDITIONS_PER_TICK per
+/// call so that a backlog is worked off over the following ticks.
+pub fn resolve_expeditions(rand: &[u64; 4]) {
+    let counter = GLOBAL_STATE.0.borrow().counter;
+    for _ in 0..MAX_EXPEDITIONS_PER_TICK {
+        match QueueEntry::pop_due(counter) {
+            None => break,
+            Some(entry) => {
+                let id = entry.expedition;
+                let mut expedition = ExpeditionInfo::get_object(id).unwrap();
+                expedition.data.resolve(rand[0] ^ id.rotate_left(17), counter);
+                expedition.store();
+                ExpeditionInfo::emit_event(id, &expedition.data);
+            }
+        }
+    }
+}
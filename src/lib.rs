@@ -7,6 +7,13 @@ pub mod player;
 pub mod settlement;
 pub mod state;
 pub mod nugget;
+pub mod expedition;
+pub mod tool;
+pub mod duel;
+pub mod warehouse;
+pub mod achievement;
+pub mod session;
+pub mod audit;
 
 use crate::config::Config;
 use crate::state::{GlobalState, Transaction};
@@ -3,7 +3,53 @@ use serde::Serialize;
 use zkwasm_rest_abi::{StorageData, MERKLE_MAP};
 use zkwasm_rest_convention::{IndexedObject, Position};
 
-use crate::error::ERROR_NUGGET_ATTRIBUTES_ALL_EXPLORED;
+use crate::config::{
+    ExploreCost, DECAY_PERIOD, DECAY_PERCENT, INSURANCE_REFUND_PERCENT, INSURANCE_THRESHOLD,
+    MAINTAIN_BASE_FEE, MAX_DECAY_PERCENT, STAKE_YIELD_DIVISOR,
+};
+use crate::error::*;
+
+// nugget status
+pub const NUGGET_ACTIVE: u64 = 0;
+pub const NUGGET_RECYCLED: u64 = 1;
+pub const NUGGET_STAKED: u64 = 2;
+pub const NUGGET_EXPEDITION: u64 = 3;
+pub const NUGGET_CONSUMED: u64 = 4;
+pub const NUGGET_DUEL: u64 = 5;
+pub const NUGGET_STORED: u64 = 6;
+
+// nugget rarity, graded by the sum of its attributes
+pub const RARITY_COMMON: u64 = 0;
+pub const RARITY_RARE: u64 = 1;
+pub const RARITY_EPIC: u64 = 2;
+pub const RARITY_LEGENDARY: u64 = 3;
+
+// nugget features
+pub const FEATURE_PLAIN: u64 = 0;
+pub const FEATURE_CHEAP_DRILL: u64 = 1;
+pub const FEATURE_DEEP_VEIN: u64 = 2;
+pub const FEATURE_ROYALTY: u64 = 3;
+pub const FEATURE_REFINERY: u64 = 4;
+pub const FEATURE_STEADY: u64 = 5;
+pub const FEATURE_OVERCLOCK: u64 = 6;
+pub const FEATURE_LUCKY: u64 = 7;
+
+// explore outcomes
+pub const EXPLORE_FAIL: u64 = 0;
+pub const EXPLORE_NORMAL: u64 = 1;
+pub const EXPLORE_CRITICAL: u64 = 2;
+pub const EXPLORE_COLLAPSE: u64 = 3;
+pub const EXPLORE_REROLL: u64 = 4;
+
+// `maintained` of a nugget stored before decay existed, it does not decay
+// until it is first explored or maintained
+pub const NOT_MAINTAINED: u64 = u64::MAX;
+
+// flat part of the reroll cost, doubled on every reroll of the same nugget
+const REROLL_BASE_FEE: u64 = 10;
+
+// attributes from this index onwards are cleared by a refine
+const REFINE_KEPT_ATTRIBUTES: usize = 4;
 
 #[derive(Clone, Serialize, Default, Copy)]
 pub struct BidInfo {
@@ -19,6 +65,10 @@ pub struct NuggetInfo {
     pub feature: u64,
     pub sysprice: u64,
     pub askprice: u64,
+    pub status: u64,
+    pub rerolls: u64,
+    pub maintained: u64,
+    pub staked: u64,
     pub bid: Option<BidInfo>,
 }
 
@@ -26,7 +76,7 @@ impl StorageData for NuggetInfo {
     fn from_data(u64data: &mut IterMut<u64>) -> Self {
         let id = *u64data.next().unwrap();
         let attributes = (*u64data.next().unwrap()).to_le_bytes();
-        let cycle = *u64data.next().unwrap();
+        let mut cycle = *u64data.next().unwrap();
         let feature = *u64data.next().unwrap();
         let sysprice = *u64data.next().unwrap();
         let askprice = *u64data.next().unwrap();
@@ -38,6 +88,19 @@ impl StorageData for NuggetInfo {
                 bidder: [*u64data.next().unwrap(), *u64data.next().unwrap()]
             })
         }
+        // fields appended after the bid are missing from older records, which
+        // marked a nugget sold to the system by setting its cycle to 1
+        let status = match u64data.next() {
+            Some(v) => *v,
+            None if cycle == 1 => {
+                cycle = 0;
+                NUGGET_RECYCLED
+            },
+            None => NUGGET_ACTIVE,
+        };
+        let rerolls = u64data.next().map_or(0, |v| *v);
+        let maintained = u64data.next().map_or(NOT_MAINTAINED, |v| *v);
+        let staked = u64data.next().map_or(0, |v| *v);
         NuggetInfo {
             id,
             attributes,
@@ -45,6 +108,10 @@ impl StorageData for NuggetInfo {
             feature,
             sysprice,
             askprice,
+            status,
+            rerolls,
+            maintained,
+            staked,
             bid: bidder,
         }
     }
@@ -63,11 +130,15 @@ impl StorageData for NuggetInfo {
                 data.push(b.bidder[1]);
             },
         }
+        data.push(self.status);
+        data.push(self.rerolls);
+        data.push(self.maintained);
+        data.push(self.staked);
     }
 }
 
 impl NuggetInfo {
-    pub fn new(id: u64, rand: u64) -> Self {
+    pub fn new(id: u64, rand: u64, counter: u64) -> Self {
        let c = rand.to_le_bytes();
        NuggetInfo {
            id,
@@ -76,19 +147,288 @@ impl NuggetInfo {
            feature: rand % 8,
            sysprice: 0,
            askprice: 0,
+           status: NUGGET_ACTIVE,
+           rerolls: 0,
+           maintained: counter,
+           staked: 0,
            bid: None
        }
     }
 
-    pub fn explore(&mut self, rand: u64) -> Result<(), u32> {
+    // nugget produced by a recipe with guaranteed attributes
+    pub fn crafted(id: u64, feature: u64, attributes: [u8; 8], counter: u64) -> Self {
+        NuggetInfo {
+            attributes,
+            feature,
+            ..NuggetInfo::new(id, 0, counter)
+        }
+    }
+
+    // graded on the same 1 to 10 scale compute_sysprice uses, the seed slot
+    // holds a raw byte that would otherwise outweigh every revealed slot
+    pub fn attribute_value(c: u8) -> u64 {
+        if c == 0 { 0 } else { (c as u64 - 1) % 10 + 1 }
+    }
+
+    pub fn rarity(&self) -> u64 {
+        let sum: u64 = self.attributes.iter().map(|c| NuggetInfo::attribute_value(*c)).sum();
+        if sum >= 60 {
+            RARITY_LEGENDARY
+        } else if sum >= 48 {
+            RARITY_EPIC
+        } else if sum >= 36 {
+            RARITY_RARE
+        } else {
+            RARITY_COMMON
+        }
+    }
+
+    fn attribute_ceiling(&self) -> u8 {
+        if self.feature == FEATURE_DEEP_VEIN { 10 } else { 9 }
+    }
+
+    fn roll_attribute(&self, rand: u64) -> u8 {
         let r = rand.to_le_bytes();
-        for c in self.attributes.iter_mut() {
-            if *c == 0 {
-                *c = (r[0].bitxor(r[1]) % 9) + 1;
-                return Ok(())
+        let ceiling = self.attribute_ceiling();
+        let mut v = (r[0].bitxor(r[1]) % ceiling) + 1;
+        if self.feature == FEATURE_LUCKY {
+            v = v.max((r[2].bitxor(r[3]) % ceiling) + 1);
+        }
+        if self.feature == FEATURE_STEADY {
+            v = v.max(3);
+        }
+        v
+    }
+
+    fn reveal_next(&mut self, v: u8) -> Option<usize> {
+        let slot = self.attributes.iter().position(|c| *c == 0)?;
+        self.attributes[slot] = v;
+        Some(slot)
+    }
+
+    // reveal the next unexplored attribute without rolling an outcome
+    pub fn reveal(&mut self, rand: u64) -> Option<usize> {
+        let v = self.roll_attribute(rand);
+        self.reveal_next(v)
+    }
+
+    /// Picks the explore outcome from a percentage roll. Higher cycles fail
+    /// and collapse more often, Lucky nuggets crit more and Steady nuggets
+    /// never collapse. A scanned nugget neither fails nor collapses.
+    pub fn explore_outcome(&self, outcome_rand: u64, scanned: bool) -> u64 {
+        let roll = outcome_rand % 100;
+        let fail = if scanned { 0 } else { (10 + self.cycle * 5).min(30) };
+        let collapse = if scanned || self.feature == FEATURE_STEADY {
+            0
+        } else {
+            (5 + self.cycle * 2).min(15)
+        };
+        let critical = if self.feature == FEATURE_LUCKY { 20 } else { 10 };
+        if roll < fail {
+            EXPLORE_FAIL
+        } else if roll < fail + collapse {
+            EXPLORE_COLLAPSE
+        } else if roll < fail + collapse + critical {
+            EXPLORE_CRITICAL
+        } else {
+            EXPLORE_NORMAL
+        }
+    }
+
+    /// With `pity` set the explore always succeeds and reveals the highest
+    /// attribute the nugget can roll.
+    pub fn explore(&mut self, rand: u64, outcome_rand: u64, pity: bool, scanned: bool) -> Result<ExploreResult, u32> {
+        if self.is_fully_explored() {
+            return Err(ERROR_NUGGET_ATTRIBUTES_ALL_EXPLORED)
+        }
+        if pity {
+            let slot = self.reveal_next(self.attribute_ceiling()).unwrap();
+            return Ok(ExploreResult {
+                outcome: EXPLORE_NORMAL,
+                changed: 1 << slot,
+                attributes: self.attributes,
+            })
+        }
+        let outcome = self.explore_outcome(outcome_rand, scanned);
+        let mut changed = 0u64;
+        match outcome {
+            EXPLORE_FAIL => (),
+            EXPLORE_COLLAPSE => {
+                // halve one of the explored attributes, never below 1
+                let explored: Vec<usize> = (0..8).filter(|i| self.attributes[*i] != 0).collect();
+                if !explored.is_empty() {
+                    let slot = explored[((outcome_rand >> 8) % explored.len() as u64) as usize];
+                    self.attributes[slot] = (self.attributes[slot] / 2).max(1);
+                    changed |= 1 << slot;
+                }
+            },
+            _ => {
+                let v = self.roll_attribute(rand);
+                if let Some(slot) = self.reveal_next(v) {
+                    changed |= 1 << slot;
+                }
+                if outcome == EXPLORE_CRITICAL {
+                    let v = self.roll_attribute(rand >> 32);
+                    if let Some(slot) = self.reveal_next(v) {
+                        changed |= 1 << slot;
+                    }
+                }
             }
         }
-        Err(ERROR_NUGGET_ATTRIBUTES_ALL_EXPLORED)
+        Ok(ExploreResult {
+            outcome,
+            changed,
+            attributes: self.attributes,
+        })
+    }
+
+    pub fn reroll(&mut self, slot: u64, rand: u64) -> Result<ExploreResult, u32> {
+        if slot >= 8 {
+            return Err(INVALID_ATTRIBUTE_INDEX)
+        }
+        if self.attributes[slot as usize] == 0 {
+            return Err(ERROR_ATTRIBUTE_NOT_EXPLORED)
+        }
+        self.attributes[slot as usize] = self.roll_attribute(rand);
+        self.rerolls += 1;
+        Ok(ExploreResult {
+            outcome: EXPLORE_REROLL,
+            changed: 1 << slot,
+            attributes: self.attributes,
+        })
+    }
+
+    pub fn reroll_cost(&self, curve: &ExploreCost) -> u64 {
+        (self.explore_cost(curve) + REROLL_BASE_FEE) << self.rerolls.min(16)
+    }
+
+    pub fn is_fully_explored(&self) -> bool {
+        self.attributes.iter().all(|c| *c != 0)
+    }
+
+    // each refine cycle makes exploring more expensive
+    pub fn explore_cost(&self, curve: &ExploreCost) -> u64 {
+        let explored = self.attributes.iter().filter(|c| **c != 0).count() as u64;
+        let cost = (curve.base + curve.per_attribute * explored + self.sysprice * curve.percent / 100)
+            .min(curve.cap) * (self.cycle + 1);
+        if self.feature == FEATURE_CHEAP_DRILL {
+            cost / 2
+        } else {
+            cost
+        }
+    }
+
+    pub fn refine_cost(&self) -> u64 {
+        if self.feature == FEATURE_REFINERY {
+            self.sysprice / 4
+        } else {
+            self.sysprice / 2
+        }
+    }
+
+    // amount paid to the owner when the nugget is sold for price
+    pub fn sale_proceeds(&self, price: u64) -> u64 {
+        if self.feature == FEATURE_ROYALTY {
+            price + price / 10
+        } else {
+            price
+        }
+    }
+
+    /// Price after decay, computed lazily from the ticks elapsed since the
+    /// nugget was last explored or maintained.
+    pub fn effective_price(&self, counter: u64) -> u64 {
+        if DECAY_PERIOD == 0 || self.maintained == NOT_MAINTAINED {
+            return self.sysprice;
+        }
+        let periods = counter.saturating_sub(self.maintained) / DECAY_PERIOD;
+        let decay = (periods * DECAY_PERCENT).min(MAX_DECAY_PERCENT);
+        self.sysprice * (100 - decay) / 100
+    }
+
+    // restoring a nugget pays back the value it lost to decay
+    pub fn maintain_cost(&self, counter: u64) -> u64 {
+        MAINTAIN_BASE_FEE + self.sysprice - self.effective_price(counter)
+    }
+
+    /// Replaces the current bid with a higher one and returns the replaced
+    /// bid, whose escrow is owed back to its bidder.
+    pub fn place_bid(&mut self, bidder: &[u64; 2], price: u64) -> Result<Option<BidInfo>, u32> {
+        if self.bid.map_or(false, |b| b.bidprice >= price) {
+            return Err(ERROR_BID_PRICE_INSUFFICIENT)
+        }
+        Ok(self.bid.replace(BidInfo {
+            bidprice: price,
+            bidder: *bidder,
+        }))
+    }
+
+    // the bid paid out on a sale, it must not stay behind to be refunded later
+    pub fn settle_bid(&mut self) -> Option<BidInfo> {
+        self.bid.take()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.status != NUGGET_ACTIVE
+    }
+
+    pub fn stake(&mut self, counter: u64) -> Result<(), u32> {
+        if self.is_locked() {
+            return Err(ERROR_NUGGET_LOCKED)
+        }
+        self.status = NUGGET_STAKED;
+        self.staked = counter;
+        Ok(())
+    }
+
+    /// Yield accrued since the last collect, `staked` holds the tick of the
+    /// last payout so nothing needs to run on every tick. The current
+    /// sysprice is applied to the whole span, which is only fair because a
+    /// staked nugget is locked and cannot be explored, refined or rerolled.
+    pub fn accrued_yield(&self, counter: u64) -> u64 {
+        if self.status != NUGGET_STAKED {
+            return 0;
+        }
+        self.sysprice * counter.saturating_sub(self.staked) / STAKE_YIELD_DIVISOR
+    }
+
+    pub fn collect_yield(&mut self, counter: u64) -> u64 {
+        let amount = self.accrued_yield(counter);
+        // keep the stamp when nothing was paid so fractions keep accruing
+        if amount > 0 {
+            self.staked = counter;
+        }
+        amount
+    }
+
+    pub fn unstake(&mut self, counter: u64) -> Result<u64, u32> {
+        if self.status != NUGGET_STAKED {
+            return Err(ERROR_NUGGET_NOT_STAKED)
+        }
+        let amount = self.accrued_yield(counter);
+        self.status = NUGGET_ACTIVE;
+        self.staked = 0;
+        Ok(amount)
+    }
+
+    fn cycle_multiplier(&self) -> u64 {
+        if self.feature == FEATURE_OVERCLOCK {
+            self.cycle * 2 + 1
+        } else {
+            self.cycle + 1
+        }
+    }
+
+    // advance to the next cycle and reopen the tail attributes for exploring
+    pub fn refine(&mut self) -> Result<(), u32> {
+        if !self.is_fully_explored() {
+            return Err(ERROR_NUGGET_NOT_FULLY_EXPLORED)
+        }
+        for c in self.attributes[REFINE_KEPT_ATTRIBUTES..].iter_mut() {
+            *c = 0;
+        }
+        self.cycle += 1;
+        Ok(())
     }
 
     pub fn compute_sysprice(&mut self) {
@@ -110,12 +450,323 @@ impl NuggetInfo {
                 p = p * ((c as u64 - 1) % 10)
             }
         }
-        self.sysprice = p;
+        self.sysprice = p * self.cycle_multiplier();
+    }
+}
+
+/// Detailed result of a single explore, emitted so that clients can animate it.
+/// `changed` is a bitmask of the attribute slots touched by the outcome.
+#[derive(Clone, Serialize, Default, Copy)]
+pub struct ExploreResult {
+    pub outcome: u64,
+    pub changed: u64,
+    pub attributes: [u8; 8],
+}
+
+impl StorageData for ExploreResult {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let outcome = *u64data.next().unwrap();
+        let changed = *u64data.next().unwrap();
+        let attributes = (*u64data.next().unwrap()).to_le_bytes();
+        ExploreResult {
+            outcome,
+            changed,
+            attributes,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.outcome);
+        data.push(self.changed);
+        data.push(u64::from_le_bytes(self.attributes));
     }
 }
 
+impl ExploreResult {
+    // highest attribute revealed by the outcome, zero if nothing was revealed
+    pub fn best_revealed(&self) -> u8 {
+        if self.outcome == EXPLORE_NORMAL || self.outcome == EXPLORE_CRITICAL {
+            (0..8)
+                .filter(|i| self.changed & (1 << i) != 0)
+                .map(|i| self.attributes[i])
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        }
+    }
+
+    // refund owed to an insured explore that cost `cost`, bounded by the pool
+    pub fn insurance_payout(&self, cost: u64, pool: u64) -> u64 {
+        if self.best_revealed() < INSURANCE_THRESHOLD {
+            (cost * INSURANCE_REFUND_PERCENT / 100).min(pool)
+        } else {
+            0
+        }
+    }
+}
+
+// explore results are only emitted as events and never stored
+impl IndexedObject<ExploreResult> for ExploreResult {
+    const PREFIX: u64 = 0x1ee2;
+    const POSTFIX: u64 = 0xfee2;
+    const EVENT_NAME: u64 = 0x03;
+}
+
 impl IndexedObject<NuggetInfo> for NuggetInfo {
     const PREFIX: u64 = 0x1ee1;
     const POSTFIX: u64 = 0xfee1;
     const EVENT_NAME: u64 = 0x02;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::INSURANCE_PREMIUM_PERCENT;
+
+    fn nugget(feature: u64, cycle: u64, attributes: [u8; 8]) -> NuggetInfo {
+        NuggetInfo {
+            feature,
+            cycle,
+            attributes,
+            ..NuggetInfo::default()
+        }
+    }
+
+    fn outcomes(n: &NuggetInfo, scanned: bool, rolls: &[(u64, u64)]) {
+        for (roll, outcome) in rolls.iter() {
+            assert_eq!(n.explore_outcome(*roll, scanned), *outcome, "roll {}", roll);
+        }
+    }
+
+    #[test]
+    fn legacy_record_decodes_sold_nugget_as_recycled() {
+        // id, attributes, cycle, feature, sysprice, askprice and no bid
+        let mut sold = vec![7, 5, 1, FEATURE_PLAIN, 40, 0, 0];
+        let n = NuggetInfo::from_data(&mut sold.iter_mut());
+        assert_eq!(n.status, NUGGET_RECYCLED);
+        assert_eq!(n.cycle, 0);
+        let mut kept = vec![8, 5, 0, FEATURE_PLAIN, 40, 0, 0];
+        let n = NuggetInfo::from_data(&mut kept.iter_mut());
+        assert_eq!(n.status, NUGGET_ACTIVE);
+        // a current record keeps a refined cycle 1 nugget active
+        let mut data = vec![];
+        NuggetInfo { cycle: 1, ..n }.to_data(&mut data);
+        let n = NuggetInfo::from_data(&mut data.iter_mut());
+        assert_eq!((n.status, n.cycle), (NUGGET_ACTIVE, 1));
+    }
+
+    #[test]
+    fn legacy_record_does_not_decay_until_maintained() {
+        let mut data = vec![8, 5, 0, FEATURE_PLAIN, 40, 0, 0];
+        let mut n = NuggetInfo::from_data(&mut data.iter_mut());
+        let late = DECAY_PERIOD * 100;
+        assert_eq!(n.effective_price(late), 40);
+        assert_eq!(n.maintain_cost(late), MAINTAIN_BASE_FEE);
+        n.maintained = late;
+        assert!(n.effective_price(late + DECAY_PERIOD * 100) < 40);
+    }
+
+    #[test]
+    fn settled_bid_is_not_refunded_when_outbid() {
+        let mut n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(n.place_bid(&[1, 1], 100).unwrap().map(|b| b.bidprice), None);
+        assert_eq!(n.place_bid(&[2, 2], 100).err(), Some(ERROR_BID_PRICE_INSUFFICIENT));
+        // the sale pays out the bid of 100 and leaves no bid behind
+        let sold = n.settle_bid().unwrap();
+        assert_eq!((sold.bidder, sold.bidprice), ([1, 1], 100));
+        assert!(n.bid.is_none());
+        // a later bid, even a lower one, replaces nothing that could be refunded
+        assert!(n.place_bid(&[2, 2], 50).unwrap().is_none());
+        let outbid = n.place_bid(&[3, 3], 80).unwrap().unwrap();
+        assert_eq!((outbid.bidder, outbid.bidprice), ([2, 2], 50));
+    }
+
+    #[test]
+    fn rarity_normalizes_seed_slot() {
+        // a raw seed byte of 255 counts as 6, not 255
+        assert_eq!(nugget(FEATURE_PLAIN, 0, [255, 0, 0, 0, 0, 0, 0, 0]).rarity(), RARITY_COMMON);
+        assert_eq!(nugget(FEATURE_PLAIN, 0, [255, 9, 9, 9, 9, 0, 0, 0]).rarity(), RARITY_RARE);
+        assert_eq!(nugget(FEATURE_PLAIN, 0, [10, 9, 9, 9, 9, 9, 0, 0]).rarity(), RARITY_EPIC);
+        assert_eq!(nugget(FEATURE_PLAIN, 0, [10, 9, 9, 9, 9, 9, 9, 0]).rarity(), RARITY_LEGENDARY);
+    }
+
+    #[test]
+    fn explore_outcome_boundaries_cycle_zero() {
+        let n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        outcomes(&n, false, &[
+            (0, EXPLORE_FAIL),
+            (9, EXPLORE_FAIL),
+            (10, EXPLORE_COLLAPSE),
+            (14, EXPLORE_COLLAPSE),
+            (15, EXPLORE_CRITICAL),
+            (24, EXPLORE_CRITICAL),
+            (25, EXPLORE_NORMAL),
+            (99, EXPLORE_NORMAL),
+            (100, EXPLORE_FAIL),
+        ]);
+    }
+
+    #[test]
+    fn explore_outcome_boundaries_grow_with_cycle() {
+        let n = nugget(FEATURE_PLAIN, 2, [5, 0, 0, 0, 0, 0, 0, 0]);
+        outcomes(&n, false, &[
+            (19, EXPLORE_FAIL),
+            (20, EXPLORE_COLLAPSE),
+            (28, EXPLORE_COLLAPSE),
+            (29, EXPLORE_CRITICAL),
+            (38, EXPLORE_CRITICAL),
+            (39, EXPLORE_NORMAL),
+        ]);
+        // fail and collapse chances are capped at 30 and 15
+        let n = nugget(FEATURE_PLAIN, 10, [5, 0, 0, 0, 0, 0, 0, 0]);
+        outcomes(&n, false, &[
+            (29, EXPLORE_FAIL),
+            (30, EXPLORE_COLLAPSE),
+            (44, EXPLORE_COLLAPSE),
+            (45, EXPLORE_CRITICAL),
+            (54, EXPLORE_CRITICAL),
+            (55, EXPLORE_NORMAL),
+        ]);
+    }
+
+    #[test]
+    fn explore_outcome_boundaries_by_feature() {
+        let steady = nugget(FEATURE_STEADY, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        outcomes(&steady, false, &[
+            (9, EXPLORE_FAIL),
+            (10, EXPLORE_CRITICAL),
+            (19, EXPLORE_CRITICAL),
+            (20, EXPLORE_NORMAL),
+        ]);
+        let lucky = nugget(FEATURE_LUCKY, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        outcomes(&lucky, false, &[
+            (14, EXPLORE_COLLAPSE),
+            (15, EXPLORE_CRITICAL),
+            (34, EXPLORE_CRITICAL),
+            (35, EXPLORE_NORMAL),
+        ]);
+    }
+
+    #[test]
+    fn explore_outcome_scanned_never_fails_or_collapses() {
+        let n = nugget(FEATURE_PLAIN, 10, [5, 0, 0, 0, 0, 0, 0, 0]);
+        outcomes(&n, true, &[
+            (0, EXPLORE_CRITICAL),
+            (9, EXPLORE_CRITICAL),
+            (10, EXPLORE_NORMAL),
+            (99, EXPLORE_NORMAL),
+        ]);
+    }
+
+    #[test]
+    fn critical_explore_reveals_two_attributes() {
+        let mut n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        // the first roll uses the low bytes of rand, the second the bytes from 32
+        let rand = (3 << 32) | 2;
+        let result = n.explore(rand, 15, false, false).unwrap();
+        assert_eq!(result.outcome, EXPLORE_CRITICAL);
+        assert_eq!(result.changed, 0b110);
+        assert_eq!(n.attributes, [5, 3, 4, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn normal_explore_reveals_one_attribute() {
+        let mut n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        let result = n.explore(7, 50, false, false).unwrap();
+        assert_eq!(result.outcome, EXPLORE_NORMAL);
+        assert_eq!(result.changed, 0b10);
+        assert_eq!(n.attributes, [5, 8, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn collapse_halves_the_chosen_slot() {
+        let mut n = nugget(FEATURE_PLAIN, 0, [8, 6, 1, 0, 0, 0, 0, 0]);
+        // 310 % 100 = 10 collapses, 310 >> 8 = 1 picks the second explored slot
+        let result = n.explore(0, 310, false, false).unwrap();
+        assert_eq!(result.outcome, EXPLORE_COLLAPSE);
+        assert_eq!(result.changed, 0b10);
+        assert_eq!(n.attributes, [8, 3, 1, 0, 0, 0, 0, 0]);
+        // 610 >> 8 = 2 picks the third slot, which never drops below 1
+        let result = n.explore(0, 610, false, false).unwrap();
+        assert_eq!(result.outcome, EXPLORE_COLLAPSE);
+        assert_eq!(result.changed, 0b100);
+        assert_eq!(n.attributes, [8, 3, 1, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn pity_reveals_the_ceiling() {
+        let mut n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        // the outcome roll would fail without pity
+        let result = n.explore(0, 0, true, false).unwrap();
+        assert_eq!(result.outcome, EXPLORE_NORMAL);
+        assert_eq!(result.changed, 0b10);
+        assert_eq!(n.attributes[1], 9);
+        let mut n = nugget(FEATURE_DEEP_VEIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        n.explore(0, 0, true, false).unwrap();
+        assert_eq!(n.attributes[1], 10);
+    }
+
+    #[test]
+    fn fully_explored_nugget_cannot_be_explored() {
+        let mut n = nugget(FEATURE_PLAIN, 0, [5; 8]);
+        assert_eq!(n.explore(0, 50, false, false).err(), Some(ERROR_NUGGET_ATTRIBUTES_ALL_EXPLORED));
+    }
+
+    #[test]
+    fn insurance_refunds_fail_collapse_and_low_rolls() {
+        let n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        // fail and collapse reveal nothing
+        for roll in [0, 10] {
+            let result = n.clone().explore(0, roll, false, false).unwrap();
+            assert_eq!(result.insurance_payout(100, 1000), 30);
+        }
+        // a normal reveal of 3 and a critical reveal of 3 and 3
+        let result = n.clone().explore(2, 50, false, false).unwrap();
+        assert_eq!(result.outcome, EXPLORE_NORMAL);
+        assert_eq!(result.insurance_payout(100, 1000), 30);
+        let result = n.clone().explore(2 | (2 << 32), 20, false, false).unwrap();
+        assert_eq!(result.outcome, EXPLORE_CRITICAL);
+        assert_eq!(result.insurance_payout(100, 1000), 30);
+    }
+
+    #[test]
+    fn insurance_pays_nothing_on_high_rolls() {
+        let n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        // the seed slot is not revealed by the explore and does not count
+        let result = n.clone().explore(3, 50, false, false).unwrap();
+        assert_eq!(result.attributes[1], 4);
+        assert_eq!(result.insurance_payout(100, 1000), 0);
+        // one high reveal out of a critical is enough
+        let result = n.clone().explore(2 | (5 << 32), 20, false, false).unwrap();
+        assert_eq!(result.insurance_payout(100, 1000), 0);
+    }
+
+    #[test]
+    fn insurance_payout_is_capped_by_the_pool() {
+        let n = nugget(FEATURE_PLAIN, 0, [5, 0, 0, 0, 0, 0, 0, 0]);
+        let result = n.clone().explore(0, 0, false, false).unwrap();
+        assert_eq!(result.insurance_payout(100, 12), 12);
+        assert_eq!(result.insurance_payout(100, 0), 0);
+    }
+
+    #[test]
+    fn insurance_expected_payout_is_below_premium() {
+        // cycle 5 maximises fail and collapse, 90 is a multiple of both
+        // attribute ceilings so the attribute rolls are uniform
+        for feature in [FEATURE_PLAIN, FEATURE_DEEP_VEIN] {
+            let n = nugget(feature, 5, [5, 0, 0, 0, 0, 0, 0, 0]);
+            let mut premiums = 0;
+            let mut payouts = 0;
+            for roll in 0..100 {
+                for a in 0..90u64 {
+                    for b in 0..90u64 {
+                        let result = n.clone().explore(a | (b << 32), roll, false, false).unwrap();
+                        premiums += 100 * INSURANCE_PREMIUM_PERCENT / 100;
+                        payouts += result.insurance_payout(100, u64::MAX);
+                    }
+                }
+            }
+            assert!(payouts < premiums, "feature {}: {} >= {}", feature, payouts, premiums);
+        }
+    }
+}
@@ -1,4 +1,14 @@
-use crate::nugget::NuggetInfo;
+use crate::config::{
+    Level, CONFIG, DAILY_BASE_REWARD, DAILY_STREAK_STEP, DAILY_WINDOW, MAX_DAILY_STREAK, DEFAULT_INVENTORY_SIZE, INVENTORY_UPGRADE_BASE_PRICE, MAX_INVENTORY_SIZE,
+    PITY_LOW_ATTRIBUTE, PITY_THRESHOLD,
+};
+use crate::state::{GlobalState, GLOBAL_STATE};
+use crate::warehouse::WarehouseInfo;
+use crate::achievement::{
+    AchievementUnlocked, ACHIEVEMENT_FIRST_SALE, ACHIEVEMENT_FULLY_EXPLORED, ACHIEVEMENT_LEGENDARY,
+    ACHIEVEMENT_TEN_TRADES, TRADES_ACHIEVEMENT_COUNT,
+};
+use crate::nugget::{ExploreResult, NuggetInfo, EXPLORE_CRITICAL, EXPLORE_NORMAL, RARITY_LEGENDARY};
 use crate::Player;
 use crate::StorageData;
 use core::slice::IterMut;
@@ -8,19 +18,86 @@ use zkwasm_rest_convention::Position;
 use zkwasm_rest_convention::Wrapped;
 use crate::error::*;
 
+/// Lifetime counters shown on the player profile. Bid escrow that is
+/// refunded does not count as spent.
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct PlayerStats {
+    pub created: u64,
+    pub explored: u64,
+    pub sales: u64,
+    pub purchases: u64,
+    pub earned: u64,
+    pub spent: u64,
+    pub highest_sysprice: u64,
+    pub referrals: u64,
+}
+
+impl StorageData for PlayerStats {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        // older player records end before the stats
+        let mut next = || u64data.next().map_or(0, |v| *v);
+        PlayerStats {
+            created: next(),
+            explored: next(),
+            sales: next(),
+            purchases: next(),
+            earned: next(),
+            spent: next(),
+            highest_sysprice: next(),
+            referrals: next(),
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.created);
+        data.push(self.explored);
+        data.push(self.sales);
+        data.push(self.purchases);
+        data.push(self.earned);
+        data.push(self.spent);
+        data.push(self.highest_sysprice);
+        data.push(self.referrals);
+    }
+}
+
 #[derive(Clone, Serialize, Debug)]
 pub struct PlayerData {
     pub balance: u64,
     pub inventory_size: u64,
+    pub explore_pity: u64,
+    pub last_create: u64,
+    pub sets_claimed: u64,
+    pub warehouse: Option<u64>,
+    pub referrer: Option<[u64; 2]>,
+    pub stats: PlayerStats,
+    pub xp: u64,
+    pub level: u64,
+    pub last_claim: u64,
+    pub claim_streak: u64,
+    pub achievements: u64,
+    pub frozen: bool,
     pub inventory: Vec<u64>,
+    pub tools: Vec<u64>,
 }
 
 impl Default for PlayerData {
     fn default() -> Self {
         Self {
             balance: 0,
-            inventory_size: 4,
+            inventory_size: DEFAULT_INVENTORY_SIZE,
+            explore_pity: 0,
+            last_create: 0,
+            sets_claimed: 0,
+            warehouse: None,
+            referrer: None,
+            stats: PlayerStats::default(),
+            xp: 0,
+            level: 0,
+            last_claim: 0,
+            claim_streak: 0,
+            achievements: 0,
+            frozen: false,
             inventory: vec![],
+            tools: vec![],
         }
     }
 }
@@ -34,10 +111,50 @@ impl StorageData for PlayerData {
         for _ in 0..length {
             inventory.push(*u64data.next().unwrap());
         }
+        // fields appended after the inventory are missing from older records,
+        // a missing word reads as zero which is the default of every field
+        let explore_pity = u64data.next().map_or(0, |v| *v);
+        let last_create = u64data.next().map_or(0, |v| *v);
+        let sets_claimed = u64data.next().map_or(0, |v| *v);
+        // stored as the warehouse id plus one, zero when there is none yet
+        let warehouse = match u64data.next().map_or(0, |v| *v) {
+            0 => None,
+            v => Some(v - 1),
+        };
+        // a zero pid means the player was not referred
+        let referrer = match [u64data.next().map_or(0, |v| *v), u64data.next().map_or(0, |v| *v)] {
+            [0, 0] => None,
+            pid => Some(pid),
+        };
+        let stats = PlayerStats::from_data(u64data);
+        let xp = u64data.next().map_or(0, |v| *v);
+        let level = u64data.next().map_or(0, |v| *v);
+        let last_claim = u64data.next().map_or(0, |v| *v);
+        let claim_streak = u64data.next().map_or(0, |v| *v);
+        let achievements = u64data.next().map_or(0, |v| *v);
+        let frozen = u64data.next().map_or(0, |v| *v) != 0;
+        let length = u64data.next().map_or(0, |v| *v);
+        let mut tools = Vec::with_capacity(length as usize);
+        for _ in 0..length {
+            tools.push(*u64data.next().unwrap());
+        }
         PlayerData {
             balance,
             inventory_size,
+            explore_pity,
+            last_create,
+            sets_claimed,
+            warehouse,
+            referrer,
+            stats,
+            xp,
+            level,
+            last_claim,
+            claim_streak,
+            achievements,
+            frozen,
             inventory,
+            tools,
         }
     }
     fn to_data(&self, data: &mut Vec<u64>) {
@@ -47,9 +164,69 @@ impl StorageData for PlayerData {
         for i in 0..self.inventory.len() {
             data.push(self.inventory[i])
         }
+        data.push(self.explore_pity);
+        data.push(self.last_create);
+        data.push(self.sets_claimed);
+        data.push(self.warehouse.map_or(0, |v| v + 1));
+        let referrer = self.referrer.unwrap_or([0, 0]);
+        data.push(referrer[0]);
+        data.push(referrer[1]);
+        self.stats.to_data(data);
+        data.push(self.xp);
+        data.push(self.level);
+        data.push(self.last_claim);
+        data.push(self.claim_streak);
+        data.push(self.achievements);
+        data.push(self.frozen as u64);
+        data.push(self.tools.len() as u64);
+        for i in 0..self.tools.len() {
+            data.push(self.tools[i])
+        }
+    }
+}
+
+/// How a command addresses one of the player's nuggets. Inventory indices
+/// shift whenever a nugget leaves the inventory and are only kept for
+/// backward compatibility, new clients should address nuggets by id.
+#[derive(Clone, Copy, Debug)]
+pub enum NuggetRef {
+    Index(u64),
+    Id(u64),
+}
+
+/// Emitted when a player reaches a new level.
+#[derive(Clone, Serialize, Default)]
+pub struct LevelUp {
+    pub owner: [u64; 2],
+    pub level: u64,
+    pub xp: u64,
+}
+
+impl StorageData for LevelUp {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let owner = [*u64data.next().unwrap(), *u64data.next().unwrap()];
+        let level = *u64data.next().unwrap();
+        let xp = *u64data.next().unwrap();
+        LevelUp {
+            owner,
+            level,
+            xp,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.owner[0]);
+        data.push(self.owner[1]);
+        data.push(self.level);
+        data.push(self.xp);
     }
 }
 
+impl IndexedObject<LevelUp> for LevelUp {
+    const PREFIX: u64 = 0x1ee8;
+    const POSTFIX: u64 = 0xfee8;
+    const EVENT_NAME: u64 = 0x09;
+}
+
 pub type GamePlayer = Player<PlayerData>;
 
 pub trait Owner: Sized {
@@ -73,10 +250,192 @@ impl PlayerData {
             Err(PLAYER_NOT_ENOUGH_BALANCE)
         } else {
             self.balance -= amount;
+            self.stats.spent += amount;
             Ok(())
         }
     }
     pub fn inc_balance(&mut self, amount: u64) {
         self.balance += amount;
+        self.stats.earned += amount;
+    }
+    // give back escrow taken by cost_balance, e.g. an outbid bid
+    pub fn refund_balance(&mut self, amount: u64) {
+        self.balance += amount;
+        self.stats.spent = self.stats.spent.saturating_sub(amount);
+    }
+
+    // called whenever a nugget enters the inventory or changes in it
+    pub fn record_owned(&mut self, owner: &[u64; 2], nugget: &NuggetInfo) {
+        self.stats.highest_sysprice = self.stats.highest_sysprice.max(nugget.sysprice);
+        if nugget.is_fully_explored() {
+            self.unlock_achievement(owner, ACHIEVEMENT_FULLY_EXPLORED);
+        }
+        if nugget.rarity() == RARITY_LEGENDARY {
+            self.unlock_achievement(owner, ACHIEVEMENT_LEGENDARY);
+        }
+    }
+
+    pub fn check_trade_achievements(&mut self, owner: &[u64; 2]) {
+        if self.stats.sales > 0 {
+            self.unlock_achievement(owner, ACHIEVEMENT_FIRST_SALE);
+        }
+        if self.stats.sales + self.stats.purchases >= TRADES_ACHIEVEMENT_COUNT {
+            self.unlock_achievement(owner, ACHIEVEMENT_TEN_TRADES);
+        }
+    }
+
+    fn unlock_achievement(&mut self, owner: &[u64; 2], id: u64) {
+        if self.achievements & (1 << id) != 0 {
+            return;
+        }
+        self.achievements |= 1 << id;
+        let reward = GlobalState::draw_airdrop(CONFIG.achievements[id as usize].reward);
+        self.inc_balance(reward);
+        AchievementUnlocked::emit_event(id, &AchievementUnlocked {
+            owner: *owner,
+            achievement: id,
+            reward,
+        });
+    }
+
+    // inventory slot of the referenced nugget, checking the player owns it
+    pub fn locate_nugget(&self, target: &NuggetRef) -> Result<usize, u32> {
+        match target {
+            NuggetRef::Index(index) => {
+                if self.inventory.len() <= *index as usize {
+                    Err(INVALID_NUGGET_INDEX)
+                } else {
+                    Ok(*index as usize)
+                }
+            },
+            NuggetRef::Id(id) => self.inventory.iter()
+                .position(|x| x == id)
+                .ok_or(ERROR_NUGGET_NOT_OWNED),
+        }
+    }
+
+    pub fn perks(&self) -> &'static Level {
+        &CONFIG.levels[self.level as usize]
+    }
+
+    // purchased slots plus the bonus of the current level
+    pub fn inventory_capacity(&self) -> u64 {
+        self.inventory_size + self.perks().inventory_bonus
+    }
+
+    pub fn has_inventory_space(&self) -> bool {
+        self.inventory.len() < self.inventory_capacity() as usize
+    }
+
+    /// Adds experience and emits a level up event for `owner` when it
+    /// reaches the next level thresholds.
+    pub fn gain_xp(&mut self, owner: &[u64; 2], amount: u64) {
+        self.xp += amount;
+        let level = self.level;
+        while let Some(next) = CONFIG.levels.get(self.level as usize + 1) {
+            if self.xp < next.xp {
+                break;
+            }
+            self.level = next.level;
+        }
+        if self.level != level {
+            LevelUp::emit_event(self.level, &LevelUp {
+                owner: *owner,
+                level: self.level,
+                xp: self.xp,
+            });
+        }
+    }
+
+    pub fn push_nugget(&mut self, nuggetid: u64) -> Result<(), u32> {
+        if !self.has_inventory_space() {
+            return Err(PLAYER_NOT_ENOUGH_INVENTORY);
+        }
+        self.inventory.push(nuggetid);
+        Ok(())
+    }
+
+    pub fn inventory_upgrade_cost(&self) -> u64 {
+        INVENTORY_UPGRADE_BASE_PRICE << (self.inventory_size - DEFAULT_INVENTORY_SIZE).min(32)
+    }
+
+    pub fn upgrade_inventory(&mut self) -> Result<(), u32> {
+        if self.inventory_size >= MAX_INVENTORY_SIZE {
+            return Err(ERROR_INVENTORY_SIZE_CAP);
+        }
+        self.cost_balance(self.inventory_upgrade_cost())?;
+        self.inventory_size += 1;
+        Ok(())
+    }
+
+    /// The player's warehouse, allocated on first use.
+    pub fn open_warehouse(&mut self) -> Wrapped<WarehouseInfo> {
+        match self.warehouse {
+            Some(id) => WarehouseInfo::get_object(id).unwrap(),
+            None => {
+                let mut global = GLOBAL_STATE.0.borrow_mut();
+                let id = global.warehouse_total;
+                global.warehouse_total += 1;
+                self.warehouse = Some(id);
+                WarehouseInfo::new_object(WarehouseInfo::new(id), id)
+            }
+        }
+    }
+
+    /// Pays the reward of every collection set completed by the nuggets in the
+    /// inventory or the warehouse that has not been claimed yet. Only gaining
+    /// a nugget can complete a set, so this runs wherever a nugget enters the
+    /// inventory.
+    pub fn check_collection_sets(&mut self) {
+        let mut nuggets = self.inventory.clone();
+        if let Some(id) = self.warehouse {
+            nuggets.extend_from_slice(&WarehouseInfo::get_object(id).unwrap().data.nuggets);
+        }
+        let mut features = 0u64;
+        for nuggetid in nuggets.iter() {
+            let nugget = NuggetInfo::get_object(*nuggetid).unwrap();
+            features |= 1 << nugget.data.feature;
+        }
+        for set in CONFIG.sets.iter() {
+            let mask = set.feature_mask();
+            if self.sets_claimed & (1 << set.id) == 0 && features & mask == mask {
+                self.sets_claimed |= 1 << set.id;
+                self.inc_balance(GlobalState::draw_airdrop(set.reward));
+            }
+        }
+    }
+
+    /// Claims the daily reward from the airdrop pool, returns the amount paid.
+    /// A zero streak means the player never claimed before.
+    pub fn claim_daily(&mut self, counter: u64) -> Result<u64, u32> {
+        if self.claim_streak > 0 && counter < self.last_claim + DAILY_WINDOW {
+            return Err(ERROR_CLAIM_NOT_READY);
+        }
+        if self.claim_streak > 0 && counter < self.last_claim + 2 * DAILY_WINDOW {
+            self.claim_streak += 1;
+        } else {
+            self.claim_streak = 1;
+        }
+        self.last_claim = counter;
+        let reward = DAILY_BASE_REWARD + DAILY_STREAK_STEP * (self.claim_streak.min(MAX_DAILY_STREAK) - 1);
+        let amount = GlobalState::draw_airdrop(reward);
+        self.inc_balance(amount);
+        Ok(amount)
+    }
+
+    // the next explore is guaranteed a high roll after too many bad ones
+    pub fn pity_ready(&self) -> bool {
+        self.explore_pity >= PITY_THRESHOLD
+    }
+
+    pub fn record_explore(&mut self, result: &ExploreResult) {
+        if result.outcome == EXPLORE_NORMAL || result.outcome == EXPLORE_CRITICAL {
+            self.stats.explored += result.changed.count_ones() as u64;
+        }
+        if result.best_revealed() < PITY_LOW_ATTRIBUTE {
+            self.explore_pity += 1;
+        } else {
+            self.explore_pity = 0;
+        }
     }
 }
@@ -0,0 +1,82 @@
+use core::slice::IterMut;
+use serde::Serialize;
+use zkwasm_rest_abi::{StorageData, MERKLE_MAP};
+use crate::error::*;
+
+// permission bits of a session key, an activity is allowed through a
+// session only when its bit is set in the session mask
+pub const SESSION_EXPLORE: u64 = 1 << 0;
+pub const SESSION_CREATE: u64 = 1 << 1;
+pub const SESSION_TRADE: u64 = 1 << 2;
+pub const SESSION_MANAGE: u64 = 1 << 3;
+pub const SESSION_DUEL: u64 = 1 << 4;
+
+const SESSION_PREFIX: u64 = 0x1eea;
+const SESSION_POSTFIX: u64 = 0xfeea;
+
+/// A secondary key acting for `owner` until the `expires` tick. Sessions
+/// are stored under the pid of the session key so that `process` can
+/// resolve it before dispatching the command.
+#[derive(Clone, Serialize, Default, Debug)]
+pub struct SessionInfo {
+    pub owner: [u64; 2],
+    pub expires: u64,
+    pub permissions: u64,
+}
+
+impl StorageData for SessionInfo {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let owner = [*u64data.next().unwrap(), *u64data.next().unwrap()];
+        let expires = *u64data.next().unwrap();
+        let permissions = *u64data.next().unwrap();
+        SessionInfo {
+            owner,
+            expires,
+            permissions,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.owner[0]);
+        data.push(self.owner[1]);
+        data.push(self.expires);
+        data.push(self.permissions);
+    }
+}
+
+impl SessionInfo {
+    fn key(session: &[u64; 2]) -> [u64; 4] {
+        [SESSION_PREFIX, session[0], session[1], SESSION_POSTFIX]
+    }
+
+    pub fn get(session: &[u64; 2]) -> Option<Self> {
+        let kvpair = unsafe { &mut MERKLE_MAP };
+        let mut data = kvpair.get(&Self::key(session));
+        if data.is_empty() {
+            None
+        } else {
+            Some(SessionInfo::from_data(&mut data.iter_mut()))
+        }
+    }
+
+    pub fn store(&self, session: &[u64; 2]) {
+        let mut data = vec![];
+        self.to_data(&mut data);
+        let kvpair = unsafe { &mut MERKLE_MAP };
+        kvpair.set(&Self::key(session), data.as_slice());
+    }
+
+    pub fn revoke(session: &[u64; 2]) {
+        let kvpair = unsafe { &mut MERKLE_MAP };
+        kvpair.set(&Self::key(session), &[]);
+    }
+
+    pub fn permits(&self, permission: u64, counter: u64) -> Result<(), u32> {
+        if counter > self.expires {
+            Err(ERROR_SESSION_EXPIRED)
+        } else if permission == 0 || self.permissions & permission == 0 {
+            Err(ERROR_SESSION_NOT_PERMITTED)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -1,6 +1,11 @@
-use crate::config::ADMIN_PUBKEY;
+use crate::config::{
+    CreationConfig, ExploreCost, ADMIN_PUBKEY, DEFAULT_CREATION_CONFIG, DEFAULT_EXPLORE_COST, DEFAULT_TRADE_FEE_PERCENT, REFERRAL_BONUS,
+    REFERRER_SHARE_PERCENT,
+};
+use crate::expedition::resolve_expeditions;
 use crate::nugget::NuggetInfo;
-use crate::player::{Owner, GamePlayer};
+use crate::player::{Owner, GamePlayer, NuggetRef};
+use crate::session::SessionInfo;
 use crate::settlement::SettlementInfo;
 use crate::Player;
 use serde::Serialize;
@@ -11,6 +16,10 @@ use zkwasm_rust_sdk::require;
 use crate::command::Command;
 use crate::command::Activity;
 use crate::command::Deposit;
+use crate::command::SetExploreCost;
+use crate::command::SetCreationConfig;
+use crate::command::SetTradeFee;
+use crate::command::FreezePlayer;
 use crate::command::Withdraw;
 use crate::command::CommandHandler;
 use crate::error::*;
@@ -23,6 +32,16 @@ pub struct GlobalState {
     pub counter: u64,
     pub txsize: u64,
     pub airdrop: u64,
+    pub expedition_total: u64,
+    pub expedition_queue: u64,
+    pub tool_total: u64,
+    pub explore_cost: ExploreCost,
+    pub creation: CreationConfig,
+    pub insurance_pool: u64,
+    pub challenge_total: u64,
+    pub warehouse_total: u64,
+    pub audit_total: u64,
+    pub trade_fee_percent: u64,
 }
 
 #[derive(Serialize)]
@@ -30,6 +49,10 @@ pub struct QueryState {
     total: u64,
     counter: u64,
     airdrop: u64,
+    explore_cost: ExploreCost,
+    creation: CreationConfig,
+    insurance_pool: u64,
+    trade_fee_percent: u64,
 }
 
 const TICK: u64 = 0;
@@ -40,8 +63,48 @@ const BID_NUGGET: u64 = 6;
 const CREATE_NUGGET: u64 = 7;
 const WITHDRAW: u64 = 8;
 const DEPOSIT: u64 = 9;
+const REFINE_NUGGET: u64 = 10;
+const REROLL_ATTRIBUTE: u64 = 11;
+const MAINTAIN_NUGGET: u64 = 12;
+const STAKE_NUGGET: u64 = 13;
+const UNSTAKE_NUGGET: u64 = 14;
+const COLLECT: u64 = 15;
+const START_EXPEDITION: u64 = 16;
+const CRAFT_NUGGET: u64 = 17;
+const BUY_TOOL: u64 = 18;
+const SET_EXPLORE_COST: u64 = 19;
+const SET_CREATION_CONFIG: u64 = 20;
+const CHALLENGE: u64 = 21;
+const ACCEPT_CHALLENGE: u64 = 22;
+const CANCEL_CHALLENGE: u64 = 23;
+const UPGRADE_INVENTORY: u64 = 24;
+const STORE_NUGGET: u64 = 25;
+const RETRIEVE_NUGGET: u64 = 26;
+const CLAIM_DAILY: u64 = 27;
+const REGISTER_SESSION: u64 = 28;
+const REVOKE_SESSION: u64 = 29;
+const FREEZE_PLAYER: u64 = 30;
+const UNFREEZE_PLAYER: u64 = 31;
+const SET_TRADE_FEE: u64 = 32;
 
+// set in params[0] to address nuggets by id instead of inventory index
+const BY_NUGGET_ID: u64 = 0x100;
 
+// optional parameters are encoded as value plus one so that zero means none
+fn optional_param(params: &[u64], i: usize) -> Option<u64> {
+    match params.get(i) {
+        Some(v) if *v > 0 => Some(*v - 1),
+        _ => None,
+    }
+}
+
+fn nugget_ref(by_id: bool, v: u64) -> NuggetRef {
+    if by_id {
+        NuggetRef::Id(v)
+    } else {
+        NuggetRef::Index(v)
+    }
+}
 
 impl GlobalState {
     pub fn new() -> Self {
@@ -49,7 +112,17 @@ impl GlobalState {
             total: 0,
             counter: 0,
             txsize: 0,
-            airdrop: 10000000
+            airdrop: 10000000,
+            expedition_total: 0,
+            expedition_queue: 0,
+            tool_total: 0,
+            explore_cost: DEFAULT_EXPLORE_COST,
+            creation: DEFAULT_CREATION_CONFIG,
+            insurance_pool: 0,
+            challenge_total: 0,
+            warehouse_total: 0,
+            audit_total: 0,
+            trade_fee_percent: DEFAULT_TRADE_FEE_PERCENT,
         }
     }
 
@@ -57,7 +130,11 @@ impl GlobalState {
         let total = GLOBAL_STATE.0.borrow().total;
         let counter = GLOBAL_STATE.0.borrow().counter;
         let airdrop = GLOBAL_STATE.0.borrow().airdrop;
-        serde_json::to_string(&QueryState { counter, total, airdrop }).unwrap()
+        let explore_cost = GLOBAL_STATE.0.borrow().explore_cost;
+        let creation = GLOBAL_STATE.0.borrow().creation;
+        let insurance_pool = GLOBAL_STATE.0.borrow().insurance_pool;
+        let trade_fee_percent = GLOBAL_STATE.0.borrow().trade_fee_percent;
+        serde_json::to_string(&QueryState { counter, total, airdrop, explore_cost, creation, insurance_pool, trade_fee_percent }).unwrap()
     }
 
     pub fn get_state(pid: Vec<u64>) -> String {
@@ -89,6 +166,22 @@ impl GlobalState {
         v.push(self.counter);
         v.push(self.airdrop);
         v.push(self.total);
+        v.push(self.expedition_total);
+        v.push(self.expedition_queue);
+        v.push(self.tool_total);
+        v.push(self.explore_cost.base);
+        v.push(self.explore_cost.per_attribute);
+        v.push(self.explore_cost.percent);
+        v.push(self.explore_cost.cap);
+        v.push(self.creation.fee);
+        v.push(self.creation.fee_step);
+        v.push(self.creation.supply_cap);
+        v.push(self.creation.cooldown);
+        v.push(self.insurance_pool);
+        v.push(self.challenge_total);
+        v.push(self.warehouse_total);
+        v.push(self.audit_total);
+        v.push(self.trade_fee_percent);
         let kvpair = unsafe { &mut MERKLE_MAP };
         kvpair.set(&[0, 0, 0, 0], v.as_slice());
     }
@@ -101,9 +194,42 @@ impl GlobalState {
             let counter = *u64data.next().unwrap();
             let airdrop = *u64data.next().unwrap();
             let total = *u64data.next().unwrap();
+            // fields appended after the original record are missing from
+            // state stored by older versions and keep their defaults
+            let mut next = |default: u64| u64data.next().map_or(default, |v| *v);
+            let expedition_total = next(self.expedition_total);
+            let expedition_queue = next(self.expedition_queue);
+            let tool_total = next(self.tool_total);
+            let explore_cost = ExploreCost {
+                base: next(self.explore_cost.base),
+                per_attribute: next(self.explore_cost.per_attribute),
+                percent: next(self.explore_cost.percent),
+                cap: next(self.explore_cost.cap),
+            };
+            let creation = CreationConfig {
+                fee: next(self.creation.fee),
+                fee_step: next(self.creation.fee_step),
+                supply_cap: next(self.creation.supply_cap),
+                cooldown: next(self.creation.cooldown),
+            };
+            let insurance_pool = next(self.insurance_pool);
+            let challenge_total = next(self.challenge_total);
+            let warehouse_total = next(self.warehouse_total);
+            let audit_total = next(self.audit_total);
+            let trade_fee_percent = next(self.trade_fee_percent);
             self.counter = counter;
             self.airdrop = airdrop;
             self.total = total;
+            self.expedition_total = expedition_total;
+            self.expedition_queue = expedition_queue;
+            self.tool_total = tool_total;
+            self.explore_cost = explore_cost;
+            self.creation = creation;
+            self.insurance_pool = insurance_pool;
+            self.challenge_total = challenge_total;
+            self.warehouse_total = warehouse_total;
+            self.audit_total = audit_total;
+            self.trade_fee_percent = trade_fee_percent;
         }
     }
 
@@ -118,6 +244,26 @@ impl GlobalState {
     pub fn get_counter() -> u64 {
         GLOBAL_STATE.0.borrow().counter
     }
+
+    pub fn get_trade_fee_percent() -> u64 {
+        GLOBAL_STATE.0.borrow().trade_fee_percent
+    }
+
+    pub fn get_explore_cost() -> ExploreCost {
+        GLOBAL_STATE.0.borrow().explore_cost
+    }
+
+    pub fn get_creation_config() -> CreationConfig {
+        GLOBAL_STATE.0.borrow().creation
+    }
+
+    // take up to amount out of the airdrop pool, returns what was taken
+    pub fn draw_airdrop(amount: u64) -> u64 {
+        let mut global = GLOBAL_STATE.0.borrow_mut();
+        let amount = amount.min(global.airdrop);
+        global.airdrop -= amount;
+        amount
+    }
 }
 
 pub struct SafeState(pub RefCell<GlobalState>);
@@ -140,6 +286,7 @@ impl Transaction {
     pub fn decode(params: &[u64]) -> Self {
         let command = params[0] & 0xff;
         let nonce = params[0] >> 16;
+        let by_id = params[0] & BY_NUGGET_ID != 0;
         let command = if command == WITHDRAW {
             Command::Withdraw (Withdraw {
                 data: [params[2], params[3], params[4]]
@@ -148,16 +295,92 @@ impl Transaction {
             Command::Deposit (Deposit {
                 data: [params[2], params[3], params[4]]
             })
+        } else if command == SET_EXPLORE_COST {
+            Command::SetExploreCost (SetExploreCost {
+                data: [params[1], params[2], params[3], params[4]]
+            })
+        } else if command == SET_CREATION_CONFIG {
+            Command::SetCreationConfig (SetCreationConfig {
+                data: [params[1], params[2], params[3], params[4]]
+            })
+        } else if command == SET_TRADE_FEE {
+            Command::SetTradeFee (SetTradeFee {
+                percent: params[1]
+            })
+        } else if command == FREEZE_PLAYER || command == UNFREEZE_PLAYER {
+            Command::FreezePlayer (FreezePlayer {
+                pid: [params[1], params[2]],
+                frozen: command == FREEZE_PLAYER,
+            })
         } else if command == INSTALL_PLAYER {
-            Command::InstallPlayer
+            // params[1..2] optionally hold the pid of the referring player
+            let referrer = match (params.get(1), params.get(2)) {
+                (Some(a), Some(b)) if *a != 0 || *b != 0 => Some([*a, *b]),
+                _ => None,
+            };
+            Command::InstallPlayer(referrer)
         } else if command == EXPLORE_NUGGET {
-            Command::Activity (Activity::Explore(params[1]))
+            // params[2] holds the tool id plus one, zero explores without a tool
+            // and a non-zero params[3] buys insurance on the explore
+            Command::Activity (Activity::Explore(
+                nugget_ref(by_id, params[1]),
+                optional_param(params, 2),
+                params.get(3).map_or(false, |v| *v != 0)
+            ))
         } else if command == SELL_NUGGET {
-            Command::Activity (Activity::Sell(params[1]))
+            Command::Activity (Activity::Sell(nugget_ref(by_id, params[1])))
         } else if command == BID_NUGGET {
             Command::Activity (Activity::Bid(params[1], params[2]))
         } else if command == CREATE_NUGGET {
             Command::Activity (Activity::Create)
+        } else if command == REFINE_NUGGET {
+            Command::Activity (Activity::Refine(nugget_ref(by_id, params[1])))
+        } else if command == REROLL_ATTRIBUTE {
+            Command::Activity (Activity::Reroll(nugget_ref(by_id, params[1]), params[2]))
+        } else if command == MAINTAIN_NUGGET {
+            Command::Activity (Activity::Maintain(nugget_ref(by_id, params[1])))
+        } else if command == STAKE_NUGGET {
+            Command::Activity (Activity::Stake(nugget_ref(by_id, params[1])))
+        } else if command == UNSTAKE_NUGGET {
+            Command::Activity (Activity::Unstake(nugget_ref(by_id, params[1])))
+        } else if command == COLLECT {
+            Command::Activity (Activity::Collect)
+        } else if command == START_EXPEDITION {
+            Command::Activity (Activity::Expedition(nugget_ref(by_id, params[1]), params[2]))
+        } else if command == CRAFT_NUGGET {
+            Command::Activity (Activity::Craft(
+                params[1],
+                params[2..].iter().map(|v| nugget_ref(by_id, *v)).collect()
+            ))
+        } else if command == BUY_TOOL {
+            Command::Activity (Activity::BuyTool(params[1]))
+        } else if command == CHALLENGE {
+            // the low 32 bits of params[1] are the nugget index or id, bit 32
+            // makes the loser's nugget part of the prize
+            Command::Activity (Activity::Challenge(
+                nugget_ref(by_id, params[1] & 0xffffffff),
+                [params[2], params[3]],
+                params[4],
+                (params[1] >> 32) & 1 == 1
+            ))
+        } else if command == ACCEPT_CHALLENGE {
+            Command::Activity (Activity::AcceptChallenge(params[1], nugget_ref(by_id, params[2])))
+        } else if command == CANCEL_CHALLENGE {
+            Command::Activity (Activity::CancelChallenge(params[1]))
+        } else if command == UPGRADE_INVENTORY {
+            Command::Activity (Activity::UpgradeInventory)
+        } else if command == STORE_NUGGET {
+            Command::Activity (Activity::Store(nugget_ref(by_id, params[1])))
+        } else if command == RETRIEVE_NUGGET {
+            Command::Activity (Activity::Retrieve(params[1]))
+        } else if command == CLAIM_DAILY {
+            Command::Activity (Activity::ClaimDaily)
+        } else if command == REGISTER_SESSION {
+            // params[1..2] are the pid of the session key, params[3] the
+            // expiry tick and params[4] the permission mask
+            Command::Activity (Activity::RegisterSession([params[1], params[2]], params[3], params[4]))
+        } else if command == REVOKE_SESSION {
+            Command::Activity (Activity::RevokeSession([params[1], params[2]]))
         } else {
             unsafe {zkwasm_rust_sdk::require(command == TICK)};
             Command::Tick
@@ -168,11 +391,17 @@ impl Transaction {
         }
     }
 
-    pub fn create_player(&self, pkey: &[u64; 4]) -> Result<(), u32> {
+    pub fn create_player(&self, pkey: &[u64; 4], referrer: &Option<[u64; 2]>) -> Result<(), u32> {
         let player = GamePlayer::get(pkey);
         match player {
             Some(_) => Err(ERROR_PLAYER_ALREADY_EXIST),
             None => {
+                // only an existing player other than the new one can refer
+                let mut referring = match referrer {
+                    Some(r) if *r == GamePlayer::pkey_to_pid(pkey) => return Err(ERROR_INVALID_REFERRER),
+                    Some(r) => Some(GamePlayer::get_from_pid(r).ok_or(ERROR_INVALID_REFERRER)?),
+                    None => None,
+                };
                 let mut player = Player::new(pkey);
                 if GLOBAL_STATE.0.borrow().airdrop > 500 {
                     player.data.balance = 500;
@@ -180,6 +409,20 @@ impl Transaction {
                 } else {
                     player.data.balance = 0;
                 }
+                if let Some(referring) = referring.as_mut() {
+                    let bonus = GlobalState::draw_airdrop(REFERRAL_BONUS);
+                    let share = bonus * REFERRER_SHARE_PERCENT / 100;
+                    referring.data.inc_balance(share);
+                    referring.data.stats.referrals += 1;
+                    player.data.inc_balance(bonus - share);
+                    player.data.referrer = *referrer;
+                    referring.store();
+                }
+                // a key that installs its own player no longer acts as a session
+                let pid = GamePlayer::pkey_to_pid(pkey);
+                if SessionInfo::get(&pid).is_some() {
+                    SessionInfo::revoke(&pid);
+                }
                 player.store();
                 Ok(())
             }
@@ -197,13 +440,24 @@ impl Transaction {
     pub fn process(&self, pkey: &[u64; 4], rand: &[u64; 4]) -> Vec<u64> {
         let pid = GamePlayer::pkey_to_pid(&pkey);
         let counter = GLOBAL_STATE.0.borrow_mut().counter;
+        // a registered session key acts for its owner within its permissions,
+        // withdrawals, installs and admin commands always run for the key itself
+        let (pid, denied) = match (&self.command, SessionInfo::get(&pid)) {
+            (Command::Activity(cmd), Some(session)) => (
+                session.owner,
+                session.permits(cmd.permission(), counter).map_or_else(|e| e, |_| 0),
+            ),
+            _ => (pid, 0),
+        };
         let e = match &self.command {
+            _ if denied != 0 => denied,
             Command::Tick => {
                 unsafe { require(*pkey == *ADMIN_PUBKEY) };
                 self.tick();
+                resolve_expeditions(rand);
                 0
             },
-            Command::InstallPlayer => self.create_player(pkey)
+            Command::InstallPlayer(referrer) => self.create_player(pkey, referrer)
                 .map_or_else(|e| e, |_| 0),
             Command::Withdraw(cmd) => cmd.handle(&pid, self.nonce, rand, counter)
                 .map_or_else(|e| e, |_| 0),
@@ -214,6 +468,26 @@ impl Transaction {
                 cmd.handle(&pid, self.nonce, rand, counter)
                     .map_or_else(|e| e, |_| 0)
             },
+            Command::SetExploreCost(cmd) => {
+                unsafe { require(*pkey == *ADMIN_PUBKEY) };
+                cmd.handle(&pid, self.nonce, rand, counter)
+                    .map_or_else(|e| e, |_| 0)
+            },
+            Command::SetCreationConfig(cmd) => {
+                unsafe { require(*pkey == *ADMIN_PUBKEY) };
+                cmd.handle(&pid, self.nonce, rand, counter)
+                    .map_or_else(|e| e, |_| 0)
+            },
+            Command::SetTradeFee(cmd) => {
+                unsafe { require(*pkey == *ADMIN_PUBKEY) };
+                cmd.handle(&pid, self.nonce, rand, counter)
+                    .map_or_else(|e| e, |_| 0)
+            },
+            Command::FreezePlayer(cmd) => {
+                unsafe { require(*pkey == *ADMIN_PUBKEY) };
+                cmd.handle(&pid, self.nonce, rand, counter)
+                    .map_or_else(|e| e, |_| 0)
+            },
         };
         match self.command {
             Command::Tick => (),
@@ -0,0 +1,55 @@
+use core::slice::IterMut;
+use serde::Serialize;
+use zkwasm_rest_abi::StorageData;
+use zkwasm_rest_convention::IndexedObject;
+
+// tool kinds
+pub const TOOL_DRILL: u64 = 0;
+pub const TOOL_SCANNER: u64 = 1;
+
+#[derive(Clone, Serialize, Default, Copy)]
+pub struct ToolInfo {
+    pub id: u64,
+    pub kind: u64,
+    pub durability: u64,
+}
+
+impl StorageData for ToolInfo {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let id = *u64data.next().unwrap();
+        let kind = *u64data.next().unwrap();
+        let durability = *u64data.next().unwrap();
+        ToolInfo {
+            id,
+            kind,
+            durability,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.id);
+        data.push(self.kind);
+        data.push(self.durability);
+    }
+}
+
+impl ToolInfo {
+    pub fn new(id: u64, kind: u64, durability: u64) -> Self {
+        ToolInfo {
+            id,
+            kind,
+            durability,
+        }
+    }
+
+    // wear the tool down by one use, returns true once it breaks
+    pub fn wear(&mut self) -> bool {
+        self.durability = self.durability.saturating_sub(1);
+        self.durability == 0
+    }
+}
+
+impl IndexedObject<ToolInfo> for ToolInfo {
+    const PREFIX: u64 = 0x1ee5;
+    const POSTFIX: u64 = 0xfee5;
+    const EVENT_NAME: u64 = 0x06;
+}
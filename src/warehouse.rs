@@ -0,0 +1,68 @@
+use core::slice::IterMut;
+use serde::Serialize;
+use zkwasm_rest_abi::StorageData;
+use zkwasm_rest_convention::IndexedObject;
+use crate::config::WAREHOUSE_CAPACITY;
+use crate::error::*;
+
+/// Long term storage for nuggets the player wants to keep but not use.
+/// Stored nuggets stay locked, they have to be retrieved into the inventory
+/// before they can be explored, staked or sold.
+#[derive(Clone, Serialize, Default)]
+pub struct WarehouseInfo {
+    pub id: u64,
+    pub nuggets: Vec<u64>,
+}
+
+impl StorageData for WarehouseInfo {
+    fn from_data(u64data: &mut IterMut<u64>) -> Self {
+        let id = *u64data.next().unwrap();
+        let length = *u64data.next().unwrap();
+        let mut nuggets = Vec::with_capacity(length as usize);
+        for _ in 0..length {
+            nuggets.push(*u64data.next().unwrap());
+        }
+        WarehouseInfo {
+            id,
+            nuggets,
+        }
+    }
+    fn to_data(&self, data: &mut Vec<u64>) {
+        data.push(self.id);
+        data.push(self.nuggets.len() as u64);
+        for i in 0..self.nuggets.len() {
+            data.push(self.nuggets[i])
+        }
+    }
+}
+
+impl WarehouseInfo {
+    pub fn new(id: u64) -> Self {
+        WarehouseInfo {
+            id,
+            nuggets: vec![],
+        }
+    }
+
+    pub fn deposit(&mut self, nuggetid: u64) -> Result<(), u32> {
+        if self.nuggets.len() >= WAREHOUSE_CAPACITY {
+            return Err(ERROR_WAREHOUSE_FULL);
+        }
+        self.nuggets.push(nuggetid);
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, nuggetid: u64) -> Result<(), u32> {
+        let slot = self.nuggets.iter()
+            .position(|x| *x == nuggetid)
+            .ok_or(ERROR_NUGGET_NOT_STORED)?;
+        self.nuggets.remove(slot);
+        Ok(())
+    }
+}
+
+impl IndexedObject<WarehouseInfo> for WarehouseInfo {
+    const PREFIX: u64 = 0x1ee7;
+    const POSTFIX: u64 = 0xfee7;
+    const EVENT_NAME: u64 = 0x08;
+}
@@ -46,6 +46,8 @@ export class IndexedObject {
           bidprice: Number(iobj.data[6]),
         }
       }
+      // fields appended after the bid, which takes three words when present
+      const tail = iobj.bidder != null ? 9 : 7;
 
       return  {
         id: Number(iobj.index),
@@ -54,6 +56,10 @@ export class IndexedObject {
         feature: Number(iobj.data[3]),
         sysprice: Number(iobj.data[4]),
         askprice: Number(iobj.data[5]),
+        status: Number(iobj.data[tail] ?? 0),
+        rerolls: Number(iobj.data[tail + 1] ?? 0),
+        maintained: Number(iobj.data[tail + 2] ?? 0),
+        staked: Number(iobj.data[tail + 3] ?? 0),
         bid: bidder,
       }
     }
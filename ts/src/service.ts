@@ -79,6 +79,14 @@ service.serve();
 
 const EVENT_POSITION_UPDATE = 1;
 const EVENT_NUGGET_UPDATE = 2;
+const EVENT_EXPLORE_RESULT = 3;
+const EVENT_EXPEDITION_UPDATE = 4;
+const EVENT_TOOL_UPDATE = 6;
+const EVENT_CHALLENGE_UPDATE = 7;
+const EVENT_WAREHOUSE_UPDATE = 8;
+const EVENT_LEVEL_UP = 9;
+const EVENT_ACHIEVEMENT_UNLOCKED = 10;
+const EVENT_AUDIT_ENTRY = 11;
 
 let preemptcounter = 0;
 
@@ -151,6 +159,46 @@ async function eventCallback(arg: TxWitness, data: BigUint64Array) {
 					console.log("indexed object", doc);
 				}
 				break;
+			case EVENT_EXPLORE_RESULT:
+				{
+					console.log("explore result event:", eventData[0], "outcome", eventData[1]);
+				}
+				break;
+			case EVENT_EXPEDITION_UPDATE:
+				{
+					console.log("expedition event:", eventData[0], "status", eventData[7]);
+				}
+				break;
+			case EVENT_TOOL_UPDATE:
+				{
+					console.log("tool event:", eventData[0], "durability", eventData[3]);
+				}
+				break;
+			case EVENT_CHALLENGE_UPDATE:
+				{
+					console.log("challenge event:", eventData[0], "status", eventData[11]);
+				}
+				break;
+			case EVENT_WAREHOUSE_UPDATE:
+				{
+					console.log("warehouse event:", eventData[0], "nuggets", eventData[2]);
+				}
+				break;
+			case EVENT_LEVEL_UP:
+				{
+					console.log("level up event:", eventData[1], eventData[2], "level", eventData[3]);
+				}
+				break;
+			case EVENT_ACHIEVEMENT_UNLOCKED:
+				{
+					console.log("achievement event:", eventData[1], eventData[2], "achievement", eventData[3]);
+				}
+				break;
+			case EVENT_AUDIT_ENTRY:
+				{
+					console.log("audit event:", eventData[0], "action", eventData[2], "target", eventData[3], eventData[4]);
+				}
+				break;
 			default:
 				console.log("unknown event");
 				break;
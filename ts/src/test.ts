@@ -10,6 +10,32 @@ const BID_NUGGET = 6n;
 const CREATE_NUGGET = 7n;
 const WITHDRAW = 8n;
 const DEPOSIT = 9n;
+const REFINE_NUGGET = 10n;
+const REROLL_ATTRIBUTE = 11n;
+const MAINTAIN_NUGGET = 12n;
+const STAKE_NUGGET = 13n;
+const UNSTAKE_NUGGET = 14n;
+const COLLECT = 15n;
+const START_EXPEDITION = 16n;
+const CRAFT_NUGGET = 17n;
+const BUY_TOOL = 18n;
+const SET_EXPLORE_COST = 19n;
+const SET_CREATION_CONFIG = 20n;
+const CHALLENGE = 21n;
+const ACCEPT_CHALLENGE = 22n;
+const CANCEL_CHALLENGE = 23n;
+const UPGRADE_INVENTORY = 24n;
+const STORE_NUGGET = 25n;
+const RETRIEVE_NUGGET = 26n;
+const CLAIM_DAILY = 27n;
+const REGISTER_SESSION = 28n;
+const REVOKE_SESSION = 29n;
+const FREEZE_PLAYER = 30n;
+const UNFREEZE_PLAYER = 31n;
+const SET_TRADE_FEE = 32n;
+
+// or-ed into a command to address nuggets by id instead of inventory index
+const BY_NUGGET_ID = 0x100n;
 
 let account = "1234";
 